@@ -1,15 +1,39 @@
 use serde::Deserialize;
 
+use crate::sim::{CarStat, Difficulty};
+
+/// Opponents of the races, changed in the options
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct RaceSettings {
+    pub opponents: usize,
+    pub difficulty: Difficulty,
+    /// local players sharing the screen
+    #[serde(default = "default_players")]
+    pub players: usize,
+}
+
+impl RaceSettings {
+    pub const MAX_OPPONENTS: usize = 7;
+    pub const MAX_PLAYERS: usize = 4;
+}
+
+fn default_players() -> usize {
+    1
+}
+
+impl Default for RaceSettings {
+    fn default() -> Self {
+        Self {
+            opponents: 3,
+            difficulty: Difficulty::Medium,
+            players: 1,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub carstat: CarStat,
-}
-
-#[derive(Deserialize, Clone, Copy)]
-pub struct CarStat {
-    pub max_velocity: f32,
-    pub rotation_speed: f32,
-    pub acceleration: f32,
-    pub brake: f32,
-    pub hitbox_size: (f32, f32),
+    #[serde(default)]
+    pub race: RaceSettings,
 }
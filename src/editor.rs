@@ -0,0 +1,10 @@
+mod draft;
+mod palette;
+mod screen;
+
+pub use draft::*;
+pub use palette::*;
+pub use screen::*;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,180 @@
+use ahash::AHashMap;
+
+use crate::sim::{Rotation, Tile, TileType, MAP_SIZE};
+
+/// Tile of a cell of the grid before and after an edit
+#[derive(Clone, Copy, Debug)]
+struct Change {
+    position: [usize; 2],
+    before: Option<Tile>,
+    after: Option<Tile>,
+}
+
+#[derive(Clone, Debug)]
+enum Edit {
+    /// tiles painted or erased while a mouse button was held
+    Tiles(Vec<Change>),
+    Start {
+        before: [usize; 2],
+        after: [usize; 2],
+    },
+}
+
+/// Tiles and starting position of a level being edited, with the edits to undo and redo
+#[derive(Debug, Default)]
+pub struct Draft {
+    tiles: AHashMap<[usize; 2], Tile>,
+    starting_position: [usize; 2],
+    // changes of the stroke being painted, a single edit once it ends
+    stroke: Vec<Change>,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    modified: bool,
+}
+
+impl Draft {
+    pub fn new(starting_position: [usize; 2], tiles: &[Tile]) -> Self {
+        Self {
+            tiles: tiles.iter().map(|tile| (tile.position, *tile)).collect(),
+            starting_position,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a cell is inside the `MAP_SIZE` grid
+    pub fn contains(position: [usize; 2]) -> bool {
+        position[0] < MAP_SIZE.0 as usize && position[1] < MAP_SIZE.1 as usize
+    }
+
+    pub fn tile(&self, position: [usize; 2]) -> Option<&Tile> {
+        self.tiles.get(&position)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = &Tile> {
+        self.tiles.values()
+    }
+
+    /// Tiles in the order of their positions, for the saved file not to change between saves
+    pub fn sorted_tiles(&self) -> Vec<Tile> {
+        let mut tiles: Vec<Tile> = self.tiles.values().copied().collect();
+        tiles.sort_by_key(|tile| (tile.position[1], tile.position[0]));
+        tiles
+    }
+
+    pub fn starting_position(&self) -> [usize; 2] {
+        self.starting_position
+    }
+
+    /// Whether there are edits since the draft was loaded or saved
+    pub fn is_modified(&self) -> bool {
+        self.modified || !self.stroke.is_empty()
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.end_stroke();
+        self.modified = false;
+    }
+
+    /// Puts a tile in a cell, or erases it with `None`, as part of the current stroke
+    pub fn paint(&mut self, position: [usize; 2], tile: Option<(TileType, Rotation)>) {
+        if !Self::contains(position) {
+            return;
+        }
+        let after = tile.map(|(tile_type, rotation)| Tile {
+            position,
+            tile_type,
+            rotation,
+        });
+        let before = self.set_tile(position, after);
+        if before == after {
+            return;
+        }
+        match self
+            .stroke
+            .iter_mut()
+            .find(|change| change.position == position)
+        {
+            Some(change) => change.after = after,
+            None => self.stroke.push(Change {
+                position,
+                before,
+                after,
+            }),
+        }
+    }
+
+    /// Ends the current stroke, to be undone at once
+    pub fn end_stroke(&mut self) {
+        let stroke: Vec<Change> = std::mem::take(&mut self.stroke)
+            .into_iter()
+            .filter(|change| change.before != change.after)
+            .collect();
+        if !stroke.is_empty() {
+            self.push(Edit::Tiles(stroke));
+        }
+    }
+
+    pub fn set_start(&mut self, position: [usize; 2]) {
+        self.end_stroke();
+        if !Self::contains(position) || position == self.starting_position {
+            return;
+        }
+        self.push(Edit::Start {
+            before: self.starting_position,
+            after: position,
+        });
+        self.starting_position = position;
+    }
+
+    /// Reverts the last edit, returns false if there is none
+    pub fn undo(&mut self) -> bool {
+        self.end_stroke();
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        match &edit {
+            Edit::Tiles(changes) => {
+                for change in changes.iter().rev() {
+                    self.set_tile(change.position, change.before);
+                }
+            }
+            Edit::Start { before, .. } => self.starting_position = *before,
+        }
+        self.redo.push(edit);
+        self.modified = true;
+        true
+    }
+
+    /// Applies the last undone edit again, returns false if there is none
+    pub fn redo(&mut self) -> bool {
+        self.end_stroke();
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        match &edit {
+            Edit::Tiles(changes) => {
+                for change in changes {
+                    self.set_tile(change.position, change.after);
+                }
+            }
+            Edit::Start { after, .. } => self.starting_position = *after,
+        }
+        self.undo.push(edit);
+        self.modified = true;
+        true
+    }
+
+    // a new edit can't be followed by the ones undone before it
+    fn push(&mut self, edit: Edit) {
+        self.undo.push(edit);
+        self.redo.clear();
+        self.modified = true;
+    }
+
+    fn set_tile(&mut self, position: [usize; 2], tile: Option<Tile>) -> Option<Tile> {
+        match tile {
+            Some(tile) => self.tiles.insert(position, tile),
+            None => self.tiles.remove(&position),
+        }
+    }
+}
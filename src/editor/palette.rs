@@ -0,0 +1,126 @@
+use macroquad::{
+    color::{Color, WHITE, YELLOW},
+    math::{Rect, Vec2},
+    miniquad::window::screen_size,
+    shapes::{draw_rectangle, draw_rectangle_lines},
+    texture::Texture2D,
+};
+
+use crate::game::draw_tile;
+use crate::sim::{Rotation, TileType};
+
+/// Tile types in the order of the mapatlas, row by row
+pub const TILE_TYPES: [TileType; 15] = [
+    TileType::StartingLine,
+    TileType::HardTurnInterior,
+    TileType::SoftTurnInterior,
+    TileType::SoftTurnExterior,
+    TileType::StraightBorder,
+    TileType::Base1,
+    TileType::HardTurnExterior,
+    TileType::SoftTurnInterior2,
+    TileType::SoftTurnExterior2,
+    TileType::DiagBorder,
+    TileType::Base2,
+    TileType::Base3,
+    TileType::Base4,
+    TileType::Base5,
+    TileType::Base6,
+];
+
+// size of a tile of the palette on screen, in pixels
+const SLOT_SIZE: f32 = 56.0;
+const MARGIN: f32 = 8.0;
+
+impl Rotation {
+    /// Quarter turn clockwise
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::PiFois2 => Rotation::PiSur2,
+            Rotation::PiSur2 => Rotation::Pi,
+            Rotation::Pi => Rotation::PiFois3Sur2,
+            Rotation::PiFois3Sur2 => Rotation::PiFois2,
+        }
+    }
+}
+
+/// Tile painted by the mouse
+#[derive(Clone, Copy, Debug)]
+pub struct Brush {
+    pub tile_type: TileType,
+    pub rotation: Rotation,
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self {
+            tile_type: TileType::Base1,
+            rotation: Rotation::PiFois2,
+        }
+    }
+}
+
+impl Brush {
+    pub fn rotate(&mut self) {
+        self.rotation = self.rotation.next();
+    }
+
+    /// Next tile type of the palette, or the previous one with a negative step
+    pub fn cycle(&mut self, step: isize) {
+        let index = TILE_TYPES
+            .iter()
+            .position(|tile_type| *tile_type == self.tile_type)
+            .unwrap_or(0);
+        let index = (index as isize + step).rem_euclid(TILE_TYPES.len() as isize);
+        self.tile_type = TILE_TYPES[index as usize];
+    }
+}
+
+fn slot(index: usize) -> Rect {
+    let (sw, sh) = screen_size();
+    let width = TILE_TYPES.len() as f32 * (SLOT_SIZE + MARGIN) - MARGIN;
+    Rect::new(
+        (sw - width) * 0.5 + index as f32 * (SLOT_SIZE + MARGIN),
+        sh - SLOT_SIZE - 2.0 * MARGIN,
+        SLOT_SIZE,
+        SLOT_SIZE,
+    )
+}
+
+/// Area of the screen covered by the row of tile types, where the mouse does not paint
+pub fn palette_area() -> Rect {
+    let (first, last) = (slot(0), slot(TILE_TYPES.len() - 1));
+    Rect::new(
+        first.x - MARGIN,
+        first.y - MARGIN,
+        last.right() - first.x + 2.0 * MARGIN,
+        SLOT_SIZE + 2.0 * MARGIN,
+    )
+}
+
+/// Tile type of the palette under a point of the screen
+pub fn palette_tile_at(point: Vec2) -> Option<TileType> {
+    (0..TILE_TYPES.len())
+        .find(|index| slot(*index).contains(point))
+        .map(|index| TILE_TYPES[index])
+}
+
+/// Draws the tile types at the bottom of the screen, turned like the brush,
+/// the one of the brush framed
+pub fn draw_palette(texture: &Texture2D, brush: Brush) {
+    let area = palette_area();
+    draw_rectangle(
+        area.x,
+        area.y,
+        area.w,
+        area.h,
+        Color::new(0.0, 0.0, 0.0, 0.7),
+    );
+    for (index, tile_type) in TILE_TYPES.iter().enumerate() {
+        let slot = slot(index);
+        draw_tile(texture, *tile_type, brush.rotation, slot, WHITE);
+        if *tile_type == brush.tile_type {
+            draw_rectangle_lines(slot.x, slot.y, slot.w, slot.h, 4.0, YELLOW);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use macroquad::{
+    camera::{set_camera, set_default_camera, Camera2D},
+    color::{Color, BLACK, DARKGRAY, GREEN, WHITE},
+    input::{
+        is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, mouse_position,
+        mouse_wheel, KeyCode, MouseButton,
+    },
+    math::{vec2, Rect, Vec2},
+    miniquad::{window::screen_size, FilterMode},
+    shapes::{draw_line, draw_rectangle_lines},
+    text::{draw_text_ex, TextParams},
+    texture::load_texture,
+    time::get_frame_time,
+    window::{clear_background, next_frame},
+};
+
+use super::{draw_palette, palette_area, palette_tile_at, Brush, Draft};
+use crate::game::{draw_tile, GameState, LevelConfig};
+use crate::gui::{draw_toast, GuiResources};
+use crate::input::InputHub;
+use crate::save::{delete_level, edited_levels_dir, forget_level, save_level, Records};
+use crate::sim::{MAP_SIZE, TILE_SIZE};
+
+// pixels of the screen per pixel of the map
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 4.0;
+const ZOOM_STEP: f32 = 1.15;
+// the grid lines are left out when the tiles get too small
+const GRID_MIN_SCALE: f32 = 0.5;
+// pixels of the screen per second
+const PAN_SPEED: f32 = 800.0;
+const TEXT_SIZE: u16 = 20;
+const HELP: &str = "Left: paint   Right: erase   R: rotate   Tab: next tile   Q: pick   \
+                    P: start   Middle/arrows: pan   Wheel: zoom   Ctrl+Z/Y: undo/redo   \
+                    Ctrl+S: save   Ctrl+R: revert to shipped   Esc: leave";
+
+/// Part of the map shown on screen
+struct View {
+    target: Vec2,
+    scale: f32,
+}
+
+impl View {
+    fn camera(&self) -> Camera2D {
+        let (sw, sh) = screen_size();
+        Camera2D {
+            target: self.target,
+            zoom: vec2(2.0 * self.scale / sw, 2.0 * self.scale / sh),
+            ..Default::default()
+        }
+    }
+
+    /// Moves the map along with the mouse, by a distance of the screen
+    fn pan(&mut self, delta: Vec2) {
+        self.target = (self.target - delta / self.scale).clamp(
+            Vec2::ZERO,
+            vec2(MAP_SIZE.0 * TILE_SIZE, MAP_SIZE.1 * TILE_SIZE),
+        );
+    }
+
+    /// Zooms in or out keeping the point of the map under a point of the screen
+    fn zoom(&mut self, steps: f32, around: Vec2) {
+        let before = self.camera().screen_to_world(around);
+        self.scale = (self.scale * ZOOM_STEP.powf(steps)).clamp(MIN_SCALE, MAX_SCALE);
+        let after = self.camera().screen_to_world(around);
+        self.pan((after - before) * self.scale);
+    }
+}
+
+/// Edits a copy of a level until Escape is pressed. Saving writes it in its own file
+/// of `edited_levels_dir` and replaces `conf`, for the game to play the edited level.
+/// Reverting deletes that file and puts back the `shipped` level. The records of the
+/// level are forgotten whenever its tiles change.
+pub async fn editor(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    conf: &mut LevelConfig,
+    shipped: &LevelConfig,
+    records: &mut Records,
+) -> GameState {
+    let texture = match load_texture(conf.tiles_texture_path()).await {
+        Ok(texture) => texture,
+        Err(err) => {
+            eprintln!("[ERR] could not load {}: {err}", conf.tiles_texture_path());
+            return GameState::Menu;
+        }
+    };
+    texture.set_filter(FilterMode::Nearest);
+
+    let mut draft = Draft::new(conf.starting_position(), conf.tiles());
+    let mut brush = Brush::default();
+    let start = conf.starting_position();
+    let mut view = View {
+        target: vec2(start[0] as f32 + 0.5, start[1] as f32 + 0.5) * TILE_SIZE,
+        scale: 1.0,
+    };
+    let mut last_mouse = Vec2::from(mouse_position());
+    // set by a first Escape with unsaved edits, a second one leaves
+    let mut leaving = false;
+    // set by a first Ctrl+R, a second one reverts
+    let mut reverting = false;
+
+    loop {
+        hub.poll();
+        let mouse = Vec2::from(mouse_position());
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+        // view
+        if is_mouse_button_down(MouseButton::Middle) {
+            view.pan(mouse - last_mouse);
+        }
+        last_mouse = mouse;
+        let arrows = vec2(
+            is_key_down(KeyCode::Right) as u8 as f32 - is_key_down(KeyCode::Left) as u8 as f32,
+            is_key_down(KeyCode::Down) as u8 as f32 - is_key_down(KeyCode::Up) as u8 as f32,
+        );
+        view.pan(-arrows * PAN_SPEED * get_frame_time());
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 {
+            view.zoom(wheel.signum(), mouse);
+        }
+        let camera = view.camera();
+        let world = camera.screen_to_world(mouse);
+        let over_palette = palette_area().contains(mouse);
+        let cell = Some([
+            (world.x / TILE_SIZE) as usize,
+            (world.y / TILE_SIZE) as usize,
+        ])
+        .filter(|cell| !over_palette && world.min_element() >= 0.0 && Draft::contains(*cell));
+
+        // edits
+        if ctrl && is_key_pressed(KeyCode::Z) {
+            if shift {
+                draft.redo();
+            } else {
+                draft.undo();
+            }
+        } else if ctrl && is_key_pressed(KeyCode::Y) {
+            draft.redo();
+        } else if ctrl && is_key_pressed(KeyCode::S) {
+            save(conf, &mut draft, hub, records);
+        } else if ctrl && is_key_pressed(KeyCode::R) {
+            if reverting {
+                revert(conf, shipped, hub, records);
+                draft = Draft::new(conf.starting_position(), conf.tiles());
+            } else {
+                hub.show_toast("Ctrl+R again to revert to the shipped level".to_string());
+            }
+            reverting = !reverting;
+        }
+        if !ctrl && is_key_pressed(KeyCode::R) {
+            brush.rotate();
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            brush.cycle(if shift { -1 } else { 1 });
+        }
+        if over_palette && is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(tile_type) = palette_tile_at(mouse) {
+                brush.tile_type = tile_type;
+            }
+        }
+        if let Some(cell) = cell {
+            if is_key_pressed(KeyCode::Q) {
+                if let Some(tile) = draft.tile(cell) {
+                    brush.tile_type = tile.tile_type;
+                    brush.rotation = tile.rotation;
+                }
+            }
+            if is_key_pressed(KeyCode::P) {
+                draft.set_start(cell);
+            }
+            if is_mouse_button_down(MouseButton::Left) {
+                draft.paint(cell, Some((brush.tile_type, brush.rotation)));
+            } else if is_mouse_button_down(MouseButton::Right) {
+                draft.paint(cell, None);
+            }
+        }
+        if !is_mouse_button_down(MouseButton::Left) && !is_mouse_button_down(MouseButton::Right) {
+            draft.end_stroke();
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            if leaving || !draft.is_modified() {
+                return GameState::Menu;
+            }
+            leaving = true;
+            hub.show_toast("Unsaved changes, Escape again to leave without saving".to_string());
+        }
+
+        // map
+        clear_background(BLACK);
+        set_camera(&camera);
+        let map = vec2(MAP_SIZE.0, MAP_SIZE.1) * TILE_SIZE;
+        let line = 1.0 / view.scale;
+        if view.scale >= GRID_MIN_SCALE {
+            let (sw, sh) = screen_size();
+            let min = camera.screen_to_world(Vec2::ZERO).max(Vec2::ZERO) / TILE_SIZE;
+            let max = (camera.screen_to_world(vec2(sw, sh)) / TILE_SIZE).ceil();
+            let max = max.min(vec2(MAP_SIZE.0, MAP_SIZE.1));
+            for x in min.x as usize..=max.x as usize {
+                let x = x as f32 * TILE_SIZE;
+                draw_line(x, 0.0, x, map.y, line, DARKGRAY);
+            }
+            for y in min.y as usize..=max.y as usize {
+                let y = y as f32 * TILE_SIZE;
+                draw_line(0.0, y, map.x, y, line, DARKGRAY);
+            }
+        }
+        draw_rectangle_lines(0.0, 0.0, map.x, map.y, 2.0 * line, WHITE);
+        for tile in draft.tiles() {
+            draw_tile(
+                &texture,
+                tile.tile_type,
+                tile.rotation,
+                cell_rect(tile.position),
+                WHITE,
+            );
+        }
+        let start = cell_rect(draft.starting_position());
+        draw_rectangle_lines(start.x, start.y, start.w, start.h, 3.0 * line, GREEN);
+        if let Some(cell) = cell {
+            let dest = cell_rect(cell);
+            let ghost = Color::new(1.0, 1.0, 1.0, 0.6);
+            draw_tile(&texture, brush.tile_type, brush.rotation, dest, ghost);
+            draw_rectangle_lines(dest.x, dest.y, dest.w, dest.h, 2.0 * line, WHITE);
+        }
+
+        // interface
+        set_default_camera();
+        draw_palette(&texture, brush);
+        let modified = if draft.is_modified() { " *" } else { "" };
+        let position = cell.map_or_else(String::new, |[x, y]| format!("   ({x}, {y})"));
+        draw_text(
+            resources,
+            &format!("{}{modified}{position}", conf.name()),
+            30.0,
+        );
+        draw_text(resources, HELP, 56.0);
+        draw_toast(resources, hub);
+
+        next_frame().await;
+    }
+}
+
+fn cell_rect(position: [usize; 2]) -> Rect {
+    Rect::new(
+        position[0] as f32 * TILE_SIZE,
+        position[1] as f32 * TILE_SIZE,
+        TILE_SIZE,
+        TILE_SIZE,
+    )
+}
+
+fn draw_text(resources: &GuiResources, text: &str, y: f32) {
+    draw_text_ex(
+        text,
+        20.0,
+        y,
+        TextParams {
+            font: Some(&resources.font),
+            font_size: TEXT_SIZE,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+}
+
+/// Writes the level with the tiles of the draft, telling on screen where it went
+fn save(conf: &mut LevelConfig, draft: &mut Draft, hub: &mut InputHub, records: &mut Records) {
+    let level = conf.with_tiles(draft.starting_position(), draft.sorted_tiles());
+    match save_level(&edited_levels_dir(), &level) {
+        Ok(path) => {
+            draft.mark_saved();
+            if !level.same_layout(conf) {
+                forget_level(records, level.name());
+            }
+            // the level is saved anyway, to go on with it later
+            let message = match level.generated_fields() {
+                Ok(_) => format!("Saved {}", path.display()),
+                Err(err) => format!("Saved {}, but {err}", path.display()),
+            };
+            hub.show_toast(message);
+            *conf = level;
+        }
+        Err(err) => {
+            eprintln!("[ERR] {err}");
+            hub.show_toast(format!("Could not save {}", level.name()));
+        }
+    }
+}
+
+/// Deletes the edited copy of the level to play it as shipped again
+fn revert(
+    conf: &mut LevelConfig,
+    shipped: &LevelConfig,
+    hub: &mut InputHub,
+    records: &mut Records,
+) {
+    if let Err(err) = delete_level(&edited_levels_dir(), conf.name()) {
+        eprintln!("[ERR] {err}");
+        hub.show_toast(format!("Could not revert {}", conf.name()));
+        return;
+    }
+    if !shipped.same_layout(conf) {
+        forget_level(records, conf.name());
+    }
+    *conf = shipped.clone();
+    hub.show_toast(format!("Reverted {} to the shipped level", conf.name()));
+}
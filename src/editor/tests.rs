@@ -0,0 +1,128 @@
+use super::*;
+use crate::game::{LevelConfig, Levels};
+use crate::sim::{Rotation, Tile, TileType, MAP_SIZE, TILE_SIZE};
+
+fn tile(position: [usize; 2], tile_type: TileType) -> Tile {
+    Tile {
+        position,
+        tile_type,
+        rotation: Rotation::PiFois2,
+    }
+}
+
+#[test]
+fn a_stroke_is_undone_and_redone_at_once() {
+    let mut draft = Draft::new([1, 1], &[tile([1, 1], TileType::StartingLine)]);
+    for x in 0..3 {
+        draft.paint([x, 1], Some((TileType::Base1, Rotation::Pi)));
+    }
+    draft.paint([0, 1], None);
+    draft.end_stroke();
+    assert!(draft.is_modified());
+    assert_eq!(draft.tile([0, 1]), None);
+    assert_eq!(draft.tile([2, 1]).unwrap().rotation, Rotation::Pi);
+
+    assert!(draft.undo());
+    assert_eq!(
+        draft.sorted_tiles(),
+        vec![tile([1, 1], TileType::StartingLine)]
+    );
+    assert!(!draft.undo());
+
+    assert!(draft.redo());
+    assert_eq!(draft.sorted_tiles().len(), 2);
+    assert_eq!(draft.tile([1, 1]).unwrap().tile_type, TileType::Base1);
+    assert!(!draft.redo());
+}
+
+#[test]
+fn a_new_edit_drops_the_undone_ones() {
+    let mut draft = Draft::new([0, 0], &[]);
+    draft.set_start([4, 2]);
+    draft.paint([4, 2], Some((TileType::StartingLine, Rotation::PiFois2)));
+    draft.end_stroke();
+    assert!(draft.undo());
+    assert!(draft.undo());
+    assert_eq!(draft.starting_position(), [0, 0]);
+
+    assert!(draft.redo());
+    draft.set_start([5, 2]);
+    assert!(!draft.redo());
+    assert_eq!(draft.starting_position(), [5, 2]);
+    assert_eq!(draft.tile([4, 2]), None);
+
+    // painting over a tile with the same one is no edit
+    let mut draft = Draft::new([0, 0], &[tile([3, 3], TileType::Base2)]);
+    draft.paint([3, 3], Some((TileType::Base2, Rotation::PiFois2)));
+    draft.paint(
+        [MAP_SIZE.0 as usize, 0],
+        Some((TileType::Base2, Rotation::PiFois2)),
+    );
+    draft.end_stroke();
+    assert!(!draft.is_modified());
+    assert!(!draft.undo());
+}
+
+#[test]
+fn brush_goes_through_the_rotations_and_the_palette() {
+    let mut brush = Brush::default();
+    for _ in 0..4 {
+        brush.rotate();
+    }
+    assert_eq!(brush.rotation, Rotation::PiFois2);
+    brush.rotate();
+    assert_eq!(brush.rotation, Rotation::PiSur2);
+
+    brush.tile_type = TILE_TYPES[0];
+    brush.cycle(-1);
+    assert_eq!(brush.tile_type, TILE_TYPES[TILE_TYPES.len() - 1]);
+    brush.cycle(2);
+    assert_eq!(brush.tile_type, TILE_TYPES[1]);
+}
+
+#[test]
+fn edited_level_is_read_back_like_levels_ron() {
+    let levels = std::fs::read_to_string("assets/levels/levels.ron").unwrap();
+    let levels: Levels = ron::from_str(&levels).unwrap();
+    // the shipped levels have no authored racing line, the generated one is written in
+    let fields = levels.levels[0].generated_fields().unwrap();
+    let (racing_line, _) = fields.split_once(",checkpoints:").unwrap();
+    let conf = ron::to_string(&levels.levels[0]).unwrap();
+    let conf = conf.replacen("checkpoints:", &format!("{racing_line},checkpoints:"), 1);
+    let conf: LevelConfig = ron::from_str(&conf).unwrap();
+    let conf = &conf;
+
+    // a tile away from the circuit leaves the authored checkpoints and racing line
+    let mut draft = Draft::new(conf.starting_position(), conf.tiles());
+    draft.paint([0, 0], Some((TileType::Base3, Rotation::PiFois3Sur2)));
+    draft.end_stroke();
+    let edited = conf.with_tiles(draft.starting_position(), draft.sorted_tiles());
+    assert!(!edited.same_layout(conf));
+
+    let saved = ron::to_string(&edited).unwrap();
+    let loaded: LevelConfig = ron::from_str(&saved).unwrap();
+    assert_eq!(loaded.name(), conf.name());
+    assert_eq!(loaded.starting_position(), conf.starting_position());
+    assert_eq!(loaded.tiles(), draft.sorted_tiles().as_slice());
+    assert!(saved.contains("checkpoints"));
+    assert!(saved.contains("racing_line"));
+    let (track, shipped) = (loaded.track(), conf.track());
+    assert_eq!(track.checkpoints.len(), shipped.checkpoints.len());
+    assert_eq!(
+        loaded.racing_line(&track).points,
+        conf.racing_line(&shipped).points
+    );
+
+    // a tile changed under a checkpoint drops them, to be derived from the new tiles
+    let center = shipped.checkpoints[0].gate.center() / TILE_SIZE;
+    let cell = [center.x as usize, center.y as usize];
+    draft.paint(cell, Some((TileType::Base3, Rotation::PiFois3Sur2)));
+    draft.end_stroke();
+    let edited = conf.with_tiles(draft.starting_position(), draft.sorted_tiles());
+    let saved = ron::to_string(&edited).unwrap();
+    assert!(!saved.contains("checkpoints"));
+    assert!(!saved.contains("racing_line"));
+    assert!(conf
+        .with_tiles(conf.starting_position(), conf.tiles().to_vec())
+        .same_layout(conf));
+}
@@ -1,17 +1,30 @@
+mod boost;
 mod camera;
-mod collision;
+mod debug;
 mod gamestate;
+mod ghost;
+mod hud;
 mod level;
+mod loading;
 mod player;
+mod respawn;
 mod sound;
 mod timer;
 
+pub use boost::*;
 pub use camera::*;
-pub use collision::*;
+pub use debug::*;
 pub use gamestate::*;
+pub use ghost::*;
+pub use hud::*;
 pub use level::*;
-pub use player::Player;
+pub use loading::*;
+pub use player::{car_texture, Player, OPPONENT_TINTS};
+pub use respawn::*;
 pub use sound::*;
 pub use timer::*;
 
 pub const ZOOM: f32 = 0.1;
+
+#[cfg(test)]
+mod tests;
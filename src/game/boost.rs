@@ -0,0 +1,113 @@
+use macroquad::{
+    color::{Color, WHITE},
+    math::{vec2, Vec2},
+    rand::gen_range,
+    shapes::{draw_rectangle, draw_rectangle_lines},
+    text::{draw_text_ex, Font, TextParams},
+};
+
+use crate::sim::Car;
+
+const RAINBOW: [Color; 6] = [
+    Color::new(1.0, 0.0, 0.0, 1.0),
+    Color::new(1.0, 0.6, 0.0, 1.0),
+    Color::new(1.0, 1.0, 0.0, 1.0),
+    Color::new(0.2, 1.0, 0.2, 1.0),
+    Color::new(0.0, 0.6, 1.0, 1.0),
+    Color::new(0.5, 0.2, 1.0, 1.0),
+];
+// seconds a particle stays visible
+const PARTICLE_LIFETIME: f32 = 0.6;
+const PARTICLES_PER_SECOND: f32 = 120.0;
+
+struct Particle {
+    position: Vec2,
+    velocity: Vec2,
+    age: f32,
+    color: Color,
+}
+
+/// Rainbow particles left behind the car while it boosts
+#[derive(Default)]
+pub struct BoostTrail {
+    particles: Vec<Particle>,
+    // particles to spawn carried over to the next frame
+    spawn: f32,
+}
+
+impl BoostTrail {
+    /// `rear` is the back of the car sprite, in world coordinates
+    pub fn update(&mut self, car: &Car, rear: Vec2, delta_time: f32) {
+        for particle in &mut self.particles {
+            particle.age += delta_time;
+            particle.position += particle.velocity * delta_time;
+        }
+        self.particles
+            .retain(|particle| particle.age < PARTICLE_LIFETIME);
+
+        if !car.boosting {
+            self.spawn = 0.0;
+            return;
+        }
+        self.spawn += PARTICLES_PER_SECOND * delta_time;
+        while self.spawn >= 1.0 {
+            self.spawn -= 1.0;
+            // thrown backward and spread a little
+            let velocity = -car.heading() * gen_range(20.0, 60.0)
+                + car.heading().perp() * gen_range(-30.0, 30.0);
+            self.particles.push(Particle {
+                position: rear,
+                velocity,
+                age: 0.0,
+                color: RAINBOW[gen_range(0, RAINBOW.len())],
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.spawn = 0.0;
+    }
+
+    pub fn draw(&self) {
+        for particle in &self.particles {
+            let life = 1.0 - particle.age / PARTICLE_LIFETIME;
+            let size = 1.0 + 3.0 * life;
+            let mut color = particle.color;
+            color.a = life;
+            draw_rectangle(
+                particle.position.x - size * 0.5,
+                particle.position.y - size * 0.5,
+                size,
+                size,
+                color,
+            );
+        }
+    }
+}
+
+/// Draws the boost meter of the car in the screen space, `scale` being the size of the HUD
+pub fn draw_boost_gauge(car: &Car, position: Vec2, scale: f32, font: &Font) {
+    let size = vec2(150.0, 12.0) * scale;
+    draw_text_ex(
+        "Boost",
+        position.x,
+        position.y,
+        TextParams {
+            font: Some(font),
+            font_size: (14.0 * scale).max(1.0) as u16,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+    let gauge = position + vec2(90.0 * scale, -size.y);
+    let color = if car.boosting {
+        RAINBOW[(car.boost * 60.0) as usize % RAINBOW.len()]
+    } else if car.boost >= 1.0 {
+        RAINBOW[3]
+    } else {
+        WHITE
+    };
+    draw_rectangle(gauge.x, gauge.y, size.x * car.boost, size.y, color);
+    draw_rectangle_lines(gauge.x, gauge.y, size.x, size.y, 2.0 * scale, WHITE);
+}
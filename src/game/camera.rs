@@ -5,10 +5,12 @@ use macroquad::{
     window,
 };
 
-use super::{Player, MAP_SIZE, TILE_SIZE, ZOOM};
+use super::{Player, ZOOM};
+use crate::sim::{MAP_SIZE, TILE_SIZE};
 
 pub const SCREEN_RATIO: f32 = 1920.0 / 1080.0;
 
+/// Area of the screen a camera draws in: x, y from the top left, width and height
 pub type Viewport = (i32, i32, i32, i32);
 
 /*pub fn viewport_rect() -> Rect {
@@ -45,24 +47,54 @@ pub fn update_viewport() -> Viewport {
     }
 }
 
+/// Splits the viewport between the local players: stacked halves for two,
+/// quarters for three or four
+pub fn split_viewport(viewport: Viewport, players: usize) -> Vec<Viewport> {
+    let (x, y, w, h) = viewport;
+    match players {
+        0 | 1 => vec![viewport],
+        2 => vec![(x, y, w, h / 2), (x, y + h / 2, w, h - h / 2)],
+        _ => {
+            let (half_w, half_h) = (w / 2, h / 2);
+            [
+                (x, y, half_w, half_h),
+                (x + half_w, y, w - half_w, half_h),
+                (x, y + half_h, half_w, h - half_h),
+                (x + half_w, y + half_h, w - half_w, h - half_h),
+            ]
+            .into_iter()
+            .take(players)
+            .collect()
+        }
+    }
+}
+
+// the cameras count the y of the viewport from the bottom of the window
+fn gl_viewport(viewport: Viewport) -> Viewport {
+    let (x, y, w, h) = viewport;
+    (x, screen_size().1 as i32 - y - h, w, h)
+}
+
+fn ratio(viewport: Viewport) -> f32 {
+    viewport.2 as f32 / viewport.3.max(1) as f32
+}
+
 pub fn set_player_cam(player: &Player, viewport: Viewport) {
     let zoom = player.zoom_speed();
+    let ratio = ratio(viewport);
     set_camera(&Camera2D {
-        zoom: vec2(
-            1.0 / TILE_SIZE * zoom,
-            1.0 / TILE_SIZE * SCREEN_RATIO * zoom,
-        ),
+        zoom: vec2(1.0 / TILE_SIZE * zoom, 1.0 / TILE_SIZE * ratio * zoom),
         target: vec2(
-            player.position.x.clamp(
+            player.render_position.x.clamp(
                 TILE_SIZE / zoom,
                 TILE_SIZE * MAP_SIZE.0 - (ZOOM * TILE_SIZE / zoom),
             ),
-            player.position.y.clamp(
-                TILE_SIZE / SCREEN_RATIO / zoom,
-                TILE_SIZE * MAP_SIZE.1 - ((ZOOM / SCREEN_RATIO * TILE_SIZE) / zoom * SCREEN_RATIO),
+            player.render_position.y.clamp(
+                TILE_SIZE / ratio / zoom,
+                TILE_SIZE * MAP_SIZE.1 - ((ZOOM / ratio * TILE_SIZE) / zoom * ratio),
             ),
         ),
-        viewport: Some(viewport),
+        viewport: Some(gl_viewport(viewport)),
         ..Default::default()
     });
 }
@@ -71,16 +103,16 @@ pub fn set_background_cam(player: &Player, viewport: Viewport) {
     let mut cam = Camera2D {
         zoom: vec2(
             1.0 / TILE_SIZE * ZOOM * 2.0,
-            1.0 / TILE_SIZE * SCREEN_RATIO * ZOOM * 2.0,
+            1.0 / TILE_SIZE * ratio(viewport) * ZOOM * 2.0,
         ),
-        viewport: Some(viewport),
+        viewport: Some(gl_viewport(viewport)),
         ..Default::default()
     };
     // to clamp to screen we need the cam to go from 120 to 360 so 240px and 240/500 = 0.48
     // for y axis it's from 67.5 (120/SCREEN_RATIO) to 202.5 so 135px and 135/500 = 0.27
     cam.target = vec2(
-        player.position.x / TILE_SIZE * 0.48 + 120.0,
-        player.position.y / TILE_SIZE * 0.27 + 67.5,
+        player.render_position.x / TILE_SIZE * 0.48 + 120.0,
+        player.render_position.y / TILE_SIZE * 0.27 + 67.5,
     );
     set_camera(&cam);
 }
@@ -0,0 +1,58 @@
+use std::f32::consts::FRAC_PI_2;
+
+use macroquad::{
+    color::RED,
+    math::vec2,
+    shapes::{draw_circle, draw_line},
+};
+
+use crate::sim::{
+    tile_position_flatten, ArcBorder, Border, LineBorder, RectHitbox, Track, TILE_SIZE,
+};
+
+pub fn draw_line_border(border: &LineBorder) {
+    let end = border.end();
+    draw_line(border.start.x, border.start.y, end.x, end.y, 1.0, RED);
+    draw_circle(border.start.x, border.start.y, 1.5, RED);
+}
+
+pub fn draw_arc_border(border: &ArcBorder) {
+    const SEGMENTS: usize = 8;
+    let point = |i: usize| {
+        let angle = border.start_angle + FRAC_PI_2 * i as f32 / SEGMENTS as f32;
+        border.center + vec2(angle.cos(), angle.sin()) * border.radius
+    };
+    for i in 0..SEGMENTS {
+        let (a, b) = (point(i), point(i + 1));
+        draw_line(a.x, a.y, b.x, b.y, 1.0, RED);
+    }
+}
+
+pub fn draw_hitbox(hitbox: &impl RectHitbox) {
+    let points = hitbox.points();
+    draw_line(points[0].x, points[0].y, points[1].x, points[1].y, 1.0, RED);
+    draw_line(points[1].x, points[1].y, points[2].x, points[2].y, 1.0, RED);
+    draw_line(points[2].x, points[2].y, points[3].x, points[3].y, 1.0, RED);
+    draw_line(points[3].x, points[3].y, points[0].x, points[0].y, 1.0, RED);
+    draw_circle(hitbox.rect().x, hitbox.rect().y, 1.0, RED)
+}
+
+/// Draws the hitbox of the car and the borders of the tiles around it
+pub fn draw_collisions(hitbox: &impl RectHitbox, track: &Track) {
+    let (x, y) = (
+        (hitbox.rect().x / TILE_SIZE) as usize,
+        (hitbox.rect().y / TILE_SIZE) as usize,
+    );
+    for tx in x.saturating_sub(3)..=x + 3 {
+        for ty in y.saturating_sub(3)..=y + 3 {
+            let Some(tile) = track.tiles.get(&tile_position_flatten([tx, ty])) else {
+                continue;
+            };
+            Border::from_tile(tile).for_each(|border| match border {
+                Border::Line(border) => draw_line_border(&border),
+                Border::Arc(border) => draw_arc_border(&border),
+            });
+        }
+    }
+    draw_hitbox(hitbox);
+}
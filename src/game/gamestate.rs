@@ -1,32 +1,27 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum GameMode {
     Arcade,
-    Perso,
+    /// inputs of a replay file played back
+    Replay,
+    /// race shared with other computers of the local network
+    Online,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum GameState {
+    #[default]
     Menu,
-    Playing(GameMode),
+    /// mode and index of the level in `levels.ron`
+    Playing(GameMode, usize),
     SelectLevel,
+    /// results of the last race
     GameOver,
+    /// waiting for the next race of the host of the local network
+    Lobby,
+    /// track editor, on a copy of a level of `levels.ron` once one is chosen
+    Editor(Option<usize>),
     Credits,
     Options,
+    Controls,
     Quit,
 }
-
-impl Default for GameState {
-    fn default() -> Self {
-        Self::Menu
-    }
-}
-
-impl GameState {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn set_game_mode(gm: GameMode) -> Self {
-        GameState::Playing(gm)
-    }
-}
@@ -0,0 +1,59 @@
+use macroquad::{
+    color::Color,
+    experimental::animation::AnimatedSprite,
+    math::vec2,
+    texture::{draw_texture_ex, DrawTextureParams, Texture2D},
+};
+
+use super::player::{car_sprite, SPRITE_SIZE};
+use crate::save::GhostFrame;
+use crate::sim::SIMULATION_RATE;
+
+const GHOST_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.35);
+
+/// Translucent car replaying the best lap
+pub struct Ghost {
+    sprite: AnimatedSprite,
+    texture: Texture2D,
+}
+
+impl Ghost {
+    pub fn new(texture: &Texture2D) -> Self {
+        Self {
+            sprite: car_sprite(),
+            texture: texture.clone(),
+        }
+    }
+
+    /// Draws the ghost where the car of the recorded lap was at `lap_time`.
+    /// Nothing is drawn once the recorded lap is over.
+    pub fn draw(&mut self, frames: &[GhostFrame], lap_time: f64) {
+        let step = (lap_time * SIMULATION_RATE).max(0.0);
+        let index = step as usize;
+        let (Some(from), Some(to)) = (frames.get(index), frames.get(index + 1)) else {
+            return;
+        };
+        let alpha = step.fract() as f32;
+        let position =
+            vec2(from.position.0, from.position.1).lerp(vec2(to.position.0, to.position.1), alpha);
+        let rotation = from.rotation + (to.rotation - from.rotation) * alpha;
+
+        if self.sprite.current_animation() != from.animation {
+            self.sprite.set_animation(from.animation);
+        }
+        self.sprite.update();
+
+        draw_texture_ex(
+            &self.texture,
+            position.x - SPRITE_SIZE.0 * 0.5,
+            position.y - SPRITE_SIZE.1 * 0.5,
+            GHOST_COLOR,
+            DrawTextureParams {
+                source: Some(self.sprite.frame().source_rect),
+                dest_size: Some(vec2(SPRITE_SIZE.0, SPRITE_SIZE.1)),
+                rotation,
+                ..Default::default()
+            },
+        )
+    }
+}
@@ -0,0 +1,307 @@
+use std::f32::consts::PI;
+
+use macroquad::{
+    color::{Color, DARKGRAY, GOLD, LIGHTGRAY, RED, SKYBLUE, WHITE},
+    math::{vec2, Rect, Vec2},
+    shapes::{draw_circle, draw_line, draw_rectangle},
+    text::{draw_text_ex, get_text_center, Font, TextParams},
+};
+
+use super::{draw_boost_gauge, Viewport};
+use crate::sim::{Racer, RacingLine, Tile, TileType, Track, TILE_SIZE};
+
+// height of the viewport the sizes of the HUD are given for
+const REFERENCE_HEIGHT: f32 = 1080.0;
+// shown speed for a velocity of 1
+const SPEED_DISPLAY_FACTOR: f32 = 20.0;
+// the speed gauge goes around 3/4 of a circle
+const GAUGE_START: f32 = 0.75 * PI;
+const GAUGE_ANGLE: f32 = 1.5 * PI;
+const GAUGE_SEGMENTS: usize = 40;
+
+/// Tiles of a circuit drawn from above, fitted in a rectangle
+pub struct Minimap {
+    tiles: Vec<([usize; 2], TileType)>,
+    min: [usize; 2],
+    size: Vec2,
+    // racing line drawn over the tiles
+    line: Vec<Vec2>,
+}
+
+impl Minimap {
+    pub fn new<'a>(tiles: impl IntoIterator<Item = &'a Tile>) -> Self {
+        let tiles: Vec<_> = tiles
+            .into_iter()
+            .map(|tile| (tile.position, tile.tile_type))
+            .collect();
+        let (mut min, mut max) = ([usize::MAX; 2], [0; 2]);
+        for (position, _) in &tiles {
+            for i in 0..2 {
+                min[i] = min[i].min(position[i]);
+                max[i] = max[i].max(position[i] + 1);
+            }
+        }
+        let size = if tiles.is_empty() {
+            Vec2::ZERO
+        } else {
+            vec2((max[0] - min[0]) as f32, (max[1] - min[1]) as f32)
+        };
+        Self {
+            tiles,
+            min,
+            size,
+            line: Vec::new(),
+        }
+    }
+
+    pub fn with_line(mut self, racing_line: &RacingLine) -> Self {
+        self.line = racing_line.points.clone();
+        self
+    }
+
+    /// Part of `dest` covered by the circuit, keeping its proportions
+    pub fn fit(&self, dest: Rect) -> Rect {
+        let scale = (dest.w / self.size.x).min(dest.h / self.size.y);
+        let size = self.size * scale;
+        let offset = vec2(dest.x, dest.y) + (dest.size() - size) * 0.5;
+        Rect::new(offset.x, offset.y, size.x, size.y)
+    }
+
+    /// Position in `dest` of a point of the world
+    pub fn project(&self, dest: Rect, point: Vec2) -> Vec2 {
+        let map = self.fit(dest);
+        let min = vec2(self.min[0] as f32, self.min[1] as f32);
+        vec2(map.x, map.y) + (point / TILE_SIZE - min) * map.w / self.size.x
+    }
+
+    pub fn draw(&self, dest: Rect, background: Color) {
+        draw_rectangle(dest.x, dest.y, dest.w, dest.h, background);
+        if self.tiles.is_empty() {
+            return;
+        }
+
+        let map = self.fit(dest);
+        let scale = map.w / self.size.x;
+        for (position, tile_type) in &self.tiles {
+            let color = match tile_type {
+                TileType::StartingLine => WHITE,
+                _ => LIGHTGRAY,
+            };
+            draw_rectangle(
+                map.x + (position[0] - self.min[0]) as f32 * scale,
+                map.y + (position[1] - self.min[1]) as f32 * scale,
+                scale.max(1.0),
+                scale.max(1.0),
+                color,
+            );
+        }
+        for (i, point) in self.line.iter().enumerate() {
+            let (from, to) = (
+                self.project(dest, *point),
+                self.project(dest, self.line[(i + 1) % self.line.len()]),
+            );
+            draw_line(from.x, from.y, to.x, to.y, 1.0, SKYBLUE);
+        }
+    }
+}
+
+/// Speed, laps, times, position and minimap drawn over the race
+pub struct Hud {
+    minimap: Minimap,
+}
+
+impl Hud {
+    pub fn new(track: &Track, racing_line: &RacingLine) -> Self {
+        Self {
+            minimap: Minimap::new(track.tiles.values()).with_line(racing_line),
+        }
+    }
+
+    /// Draws the HUD of `racer` inside the viewport of its camera.
+    /// `position` is its rank among `others.len() + 1` racers,
+    /// `best_lap` the record of the level.
+    pub fn draw(
+        &self,
+        racer: &Racer,
+        best_lap: Option<f64>,
+        position: usize,
+        others: &[Vec2],
+        viewport: Viewport,
+        font: &Font,
+    ) {
+        let (x, y, w, h) = viewport;
+        let area = Rect::new(x as f32, y as f32, w as f32, h as f32);
+        let scale = area.h / REFERENCE_HEIGHT;
+        let margin = 30.0 * scale;
+        let text = |line: &str, at: Vec2, size: f32, color: Color| {
+            draw_text_ex(
+                line,
+                at.x,
+                at.y,
+                TextParams {
+                    font: Some(font),
+                    font_size: (size * scale).max(1.0) as u16,
+                    color,
+                    ..Default::default()
+                },
+            );
+        };
+        let race = &racer.race;
+
+        // times and laps on the top left
+        let left = area.x + margin;
+        let mut line_y = area.y + margin + 40.0 * scale;
+        text(
+            &format!("{:.2}s", racer.time),
+            vec2(left, line_y),
+            40.0,
+            WHITE,
+        );
+        line_y += 50.0 * scale;
+        let lap = race.lap.clamp(1, race.laps);
+        text(
+            &format!("Lap {lap}/{}", race.laps),
+            vec2(left, line_y),
+            28.0,
+            WHITE,
+        );
+        if race.checkpoint_count() > 0 {
+            line_y += 36.0 * scale;
+            text(
+                &format!("CP {}/{}", race.splits().len(), race.checkpoint_count()),
+                vec2(left, line_y),
+                20.0,
+                WHITE,
+            );
+        }
+        line_y += 36.0 * scale;
+        text(
+            &format!("Lap time: {:.2}s", race.lap_time(racer.time)),
+            vec2(left, line_y),
+            20.0,
+            WHITE,
+        );
+        let best = race
+            .lap_times
+            .iter()
+            .copied()
+            .chain(best_lap)
+            .reduce(f64::min);
+        if let Some(best) = best {
+            line_y += 30.0 * scale;
+            text(
+                &format!("Best lap: {best:.2}s"),
+                vec2(left, line_y),
+                20.0,
+                GOLD,
+            );
+        }
+
+        // position and minimap on the top right
+        let racers = others.len() + 1;
+        let position_text = format!("{}/{racers}", position.min(racers));
+        let center = get_text_center(&position_text, Some(font), (40.0 * scale) as u16, 1.0, 0.0);
+        text(
+            &position_text,
+            vec2(
+                area.right() - margin - 2.0 * center.x,
+                area.y + margin + 40.0 * scale,
+            ),
+            40.0,
+            WHITE,
+        );
+        let map_w = area.w * 0.2;
+        let dest = Rect::new(
+            area.right() - margin - map_w,
+            area.y + margin + 70.0 * scale,
+            map_w,
+            map_w * 0.6,
+        );
+        self.minimap.draw(dest, Color::new(0.0, 0.0, 0.0, 0.5));
+        for other in others {
+            let marker = self.minimap.project(dest, *other);
+            draw_circle(marker.x, marker.y, 4.0 * scale, RED);
+        }
+        let marker = self.minimap.project(dest, racer.car.position);
+        draw_circle(marker.x, marker.y, 6.0 * scale, GOLD);
+
+        // speed and boost on the bottom
+        draw_speed_gauge(
+            racer,
+            vec2(
+                area.right() - margin - 110.0 * scale,
+                area.bottom() - margin - 110.0 * scale,
+            ),
+            scale,
+            font,
+        );
+        draw_boost_gauge(&racer.car, vec2(left, area.bottom() - margin), scale, font);
+    }
+}
+
+/// Dial filled with the velocity of the car, up to the max velocity reached with a boost
+fn draw_speed_gauge(racer: &Racer, center: Vec2, scale: f32, font: &Font) {
+    let car = &racer.car;
+    let radius = 100.0 * scale;
+    let thickness = 14.0 * scale;
+    let top_velocity = car.stat.max_velocity + car.stat.boost_velocity;
+    let filled = (car.velocity.abs() / top_velocity).clamp(0.0, 1.0);
+    let point = |t: f32| {
+        let angle = GAUGE_START + t * GAUGE_ANGLE;
+        center + vec2(angle.cos(), angle.sin()) * radius
+    };
+
+    for i in 0..GAUGE_SEGMENTS {
+        let (from, to) = (
+            i as f32 / GAUGE_SEGMENTS as f32,
+            (i + 1) as f32 / GAUGE_SEGMENTS as f32,
+        );
+        let color = if to <= filled {
+            if car.boosting {
+                GOLD
+            } else {
+                WHITE
+            }
+        } else {
+            DARKGRAY
+        };
+        let (a, b) = (point(from), point(to));
+        draw_line(a.x, a.y, b.x, b.y, thickness, color);
+    }
+
+    // mark of the max velocity without boost
+    let max = car.stat.max_velocity / top_velocity;
+    let (inner, outer) = (
+        center + (point(max) - center) * 0.8,
+        center + (point(max) - center) * 1.15,
+    );
+    draw_line(inner.x, inner.y, outer.x, outer.y, 3.0 * scale, RED);
+
+    let speed = format!("{:.0}", car.velocity.abs() * SPEED_DISPLAY_FACTOR);
+    let font_size = (40.0 * scale).max(1.0) as u16;
+    let text_center = get_text_center(&speed, Some(font), font_size, 1.0, 0.0);
+    draw_text_ex(
+        &speed,
+        center.x - text_center.x,
+        center.y - text_center.y,
+        TextParams {
+            font: Some(font),
+            font_size,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+    let unit_size = (16.0 * scale).max(1.0) as u16;
+    let unit_center = get_text_center("km/h", Some(font), unit_size, 1.0, 0.0);
+    draw_text_ex(
+        "km/h",
+        center.x - unit_center.x,
+        center.y + radius * 0.45,
+        TextParams {
+            font: Some(font),
+            font_size: unit_size,
+            color: LIGHTGRAY,
+            ..Default::default()
+        },
+    );
+}
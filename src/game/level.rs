@@ -1,59 +1,189 @@
 use std::f32::consts::FRAC_PI_2;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use ahash::AHashMap;
-use macroquad::audio::{load_sound, Sound};
-use macroquad::color::WHITE;
-use macroquad::math::{vec2, Rect};
+use macroquad::audio::{load_sound_from_bytes, Sound};
+use macroquad::color::{Color, WHITE};
+use macroquad::math::{vec2, Rect, Vec2};
 use macroquad::miniquad::FilterMode;
-use macroquad::texture::{draw_texture_ex, load_texture, DrawTextureParams, Texture2D};
-use serde::Deserialize;
+use macroquad::text::Font;
+use macroquad::texture::{draw_texture_ex, DrawTextureParams, Image, Texture2D};
+use serde::{Deserialize, Serialize};
 
-use super::LineBorder;
+use super::{draw_loading_screen, Minimap};
+use crate::sim::{
+    Checkpoint, Gate, RacingLine, Rotation, Tile, TileType, Track, TrackGraph, DEFAULT_LAPS,
+    TILE_SIZE,
+};
 
-pub const TILE_SIZE: f32 = 24.0;
-pub const TILE_DIAG_SIZE: f32 = 33.941125;
-pub const MAP_SIZE: (f32, f32) = (500.0, 250.0);
+// checkpoints of the levels without any
+const GENERATED_CHECKPOINTS: usize = 3;
+// assets and generated parts of a level, loaded one after the other
+const LOADING_STEPS: f32 = 5.0;
 
 #[derive(Clone, Deserialize)]
 pub struct Levels {
     pub levels: Vec<LevelConfig>,
 }
 
-#[derive(Clone, Debug, Deserialize)]
-#[serde(rename(deserialize = "level"))]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename = "level")]
 pub struct LevelConfig {
     name: String,
     background_path: String,
     tiles_texture_path: String,
     music_path: String,
     starting_position: [usize; 2],
+    #[serde(default = "default_laps")]
+    laps: usize,
+    /// spread along the lap when there are none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    checkpoints: Vec<Checkpoint>,
+    /// waypoints of the AI cars in tiles, derived from the tiles when there are none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    racing_line: Vec<(f32, f32)>,
     tiles: Vec<Tile>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
-pub struct Tile {
-    pub position: [usize; 2],
-    pub tile_type: TileType,
-    pub rotation: Rotation,
+fn default_laps() -> usize {
+    DEFAULT_LAPS
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
-pub enum TileType {
-    StartingLine,
-    Base1,
-    Base2,
-    Base3,
-    Base4,
-    Base5,
-    Base6,
-    HardTurnInterior,
-    HardTurnExterior,
-    SoftTurnInterior,
-    SoftTurnInterior2,
-    SoftTurnExterior,
-    SoftTurnExterior2,
-    StraightBorder,
-    DiagBorder,
+impl LevelConfig {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    pub fn starting_position(&self) -> [usize; 2] {
+        self.starting_position
+    }
+
+    pub fn tiles_texture_path(&self) -> &str {
+        &self.tiles_texture_path
+    }
+
+    /// Same level with other tiles. The checkpoints and the racing line are kept
+    /// while the tiles under them are the same, otherwise they are derived from the new tiles.
+    pub fn with_tiles(&self, starting_position: [usize; 2], tiles: Vec<Tile>) -> Self {
+        let before = Track::new(self.starting_position, self.laps, Vec::new(), &self.tiles);
+        let after = Track::new(starting_position, self.laps, Vec::new(), &tiles);
+        let unchanged = |point: Vec2| before.tile_at(point) == after.tile_at(point);
+        // the order of the checkpoints and of the waypoints starts from the starting line
+        let same_start = starting_position == self.starting_position;
+        let checkpoints_kept = same_start
+            && self
+                .checkpoints
+                .iter()
+                .all(|checkpoint| gate_points(&checkpoint.gate).all(unchanged));
+        let line = &self.racing_line;
+        let racing_line_kept = same_start
+            && (0..line.len()).all(|i| {
+                let (start, end) = (line[i], line[(i + 1) % line.len()]);
+                segment_points(vec2(start.0, start.1), vec2(end.0, end.1)).all(unchanged)
+            });
+        Self {
+            starting_position,
+            checkpoints: if checkpoints_kept {
+                self.checkpoints.clone()
+            } else {
+                Vec::new()
+            },
+            racing_line: if racing_line_kept {
+                self.racing_line.clone()
+            } else {
+                Vec::new()
+            },
+            tiles,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the other level has the same starting position and tiles,
+    /// the records and the replays of one staying true on the other
+    pub fn same_layout(&self, other: &LevelConfig) -> bool {
+        let sorted = |tiles: &[Tile]| {
+            let mut tiles = tiles.to_vec();
+            tiles.sort_by_key(|tile| (tile.position[1], tile.position[0]));
+            tiles
+        };
+        self.starting_position == other.starting_position
+            && sorted(&self.tiles) == sorted(&other.tiles)
+    }
+
+    /// Map of the tiles of the circuit, drawn without loading any texture
+    pub fn thumbnail(&self) -> Minimap {
+        Minimap::new(&self.tiles)
+    }
+
+    pub fn track(&self) -> Track {
+        let mut track = Track::new(
+            self.starting_position,
+            self.laps,
+            self.checkpoints.clone(),
+            &self.tiles,
+        );
+        if track.checkpoints.is_empty() {
+            if let Some(graph) = TrackGraph::new(&track) {
+                track.checkpoints = graph.checkpoints(&track, GENERATED_CHECKPOINTS);
+            }
+        }
+        track
+    }
+
+    pub fn racing_line(&self, track: &Track) -> RacingLine {
+        if self.racing_line.is_empty() {
+            return RacingLine::from_track(track).unwrap_or_default();
+        }
+        RacingLine::new(
+            self.racing_line
+                .iter()
+                .map(|(x, y)| vec2(*x, *y) * TILE_SIZE)
+                .collect(),
+        )
+    }
+
+    /// Racing line and checkpoints derived from the tiles, as the fields of `levels.ron`
+    pub fn generated_fields(&self) -> Result<String, String> {
+        let track = self.track();
+        let racing_line = RacingLine::from_track(&track)
+            .ok_or("the starting line does not start a loop of open tiles")?;
+        // a tenth of a tile is precise enough to edit the points by hand
+        let points: Vec<(f32, f32)> = racing_line
+            .points
+            .iter()
+            .map(|point| {
+                let point = (*point / TILE_SIZE * 10.0).round() / 10.0;
+                (point.x, point.y)
+            })
+            .collect();
+        let points = ron::to_string(&points).map_err(|err| err.to_string())?;
+        let checkpoints = ron::to_string(&track.checkpoints).map_err(|err| err.to_string())?;
+        Ok(format!("racing_line:{points},checkpoints:{checkpoints}"))
+    }
+}
+
+/// Points of the map covered by a gate, in pixels, one per quarter of tile at most
+fn gate_points(gate: &Gate) -> Box<dyn Iterator<Item = Vec2>> {
+    match *gate {
+        Gate::Tiles { position, size } => Box::new((0..size[0]).flat_map(move |x| {
+            (0..size[1]).map(move |y| {
+                (vec2((position[0] + x) as f32, (position[1] + y) as f32) + 0.5) * TILE_SIZE
+            })
+        })),
+        Gate::Line { start, end } => {
+            Box::new(segment_points(vec2(start.0, start.1), vec2(end.0, end.1)))
+        }
+    }
+}
+
+/// Points of a segment expressed in tiles, in pixels, one per quarter of tile at most
+fn segment_points(start: Vec2, end: Vec2) -> impl Iterator<Item = Vec2> {
+    let steps = (start.distance(end) * 4.0).ceil().max(1.0) as usize;
+    (0..=steps).map(move |step| start.lerp(end, step as f32 / steps as f32) * TILE_SIZE)
 }
 
 impl TileType {
@@ -78,48 +208,84 @@ impl TileType {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
-pub enum Rotation {
-    PiSur2 = 1,
-    Pi = 2,
-    PiFois3Sur2 = 3,
-    PiFois2 = 0,
-}
-
 pub struct Level {
-    name: String,
+    pub name: String,
     pub background: Texture2D,
     pub tile_texture: Texture2D,
-    music: Sound,
-    pub starting_position: [usize; 2],
-    pub tiles: AHashMap<usize, Tile>,
+    pub music: Sound,
+    pub track: Track,
+    pub racing_line: RacingLine,
+}
+
+/// Assets read and decoded away from the window, with the track derived from the tiles
+struct LoadedParts {
+    background: Image,
+    music: Vec<u8>,
+    tiles: Image,
+    track: Track,
+    racing_line: RacingLine,
+}
+
+impl LoadedParts {
+    /// Loads the parts one after the other, counting them in `steps`
+    fn load(conf: &LevelConfig, steps: &AtomicUsize) -> Self {
+        let image = |path: &str, name: &str| {
+            let bytes = std::fs::read(path).expect(name);
+            Image::from_file_with_format(&bytes, None).expect(name)
+        };
+        let background = image(&conf.background_path, "background.png file");
+        steps.fetch_add(1, Ordering::Relaxed);
+        let music = std::fs::read(&conf.music_path).expect("music.wav file");
+        steps.fetch_add(1, Ordering::Relaxed);
+        let tiles = image(&conf.tiles_texture_path, "mapatlas.png file");
+        steps.fetch_add(1, Ordering::Relaxed);
+        // the checkpoints and the racing line may be generated from the tiles
+        let track = conf.track();
+        steps.fetch_add(1, Ordering::Relaxed);
+        let racing_line = conf.racing_line(&track);
+        steps.fetch_add(1, Ordering::Relaxed);
+        Self {
+            background,
+            music,
+            tiles,
+            track,
+            racing_line,
+        }
+    }
 }
 
 impl Level {
-    pub async fn load(conf: &LevelConfig) -> Self {
-        let background: Texture2D = load_texture(&conf.background_path)
-            .await
-            .expect("background.png file");
-        let music: Sound = load_sound(&conf.music_path).await.expect("music.wav file");
-        let tile_texture: Texture2D = load_texture(&conf.tiles_texture_path)
-            .await
-            .expect("mapatlas.png file");
+    /// Loads the assets of the level and derives its track on another thread,
+    /// the loading screen being drawn every frame meanwhile.
+    pub async fn load(conf: &LevelConfig, font: &Font) -> Self {
+        let steps = Arc::new(AtomicUsize::new(0));
+        let loader = {
+            let (conf, steps) = (conf.clone(), steps.clone());
+            std::thread::spawn(move || LoadedParts::load(&conf, &steps))
+        };
+        while !loader.is_finished() {
+            let progress = steps.load(Ordering::Relaxed) as f32 / LOADING_STEPS;
+            draw_loading_screen(font, &conf.name, progress).await;
+        }
+        let parts = loader.join().expect("level assets");
 
+        // only the window can create the textures and the sound
+        let background = Texture2D::from_image(&parts.background);
         background.set_filter(FilterMode::Nearest);
+        let tile_texture = Texture2D::from_image(&parts.tiles);
         tile_texture.set_filter(FilterMode::Nearest);
-
-        let mut tiles = AHashMap::<usize, Tile>::new();
-        conf.tiles.iter().for_each(|tile| {
-            tiles.insert(tile_position_flatten(tile.position), *tile);
-        });
+        let music = load_sound_from_bytes(&parts.music)
+            .await
+            .expect("music.wav file");
+        draw_loading_screen(font, &conf.name, 1.0).await;
 
         Self {
             name: conf.name.clone(),
             background,
             tile_texture,
             music,
-            starting_position: conf.starting_position,
-            tiles,
+            racing_line: parts.racing_line,
+            track: parts.track,
         }
     }
 
@@ -136,25 +302,43 @@ impl Level {
     }
 
     pub fn draw_circuit(&self) {
-        self.tiles.iter().for_each(|(_, tile)| {
-            let (x, y) = tile.tile_type.mapatlas_source();
-            draw_texture_ex(
-                &self.tile_texture,
+        self.track.tiles.iter().for_each(|(_, tile)| {
+            let dest = Rect::new(
                 TILE_SIZE * tile.position[0] as f32,
                 TILE_SIZE * tile.position[1] as f32,
+                TILE_SIZE,
+                TILE_SIZE,
+            );
+            draw_tile(
+                &self.tile_texture,
+                tile.tile_type,
+                tile.rotation,
+                dest,
                 WHITE,
-                DrawTextureParams {
-                    dest_size: Some(vec2(TILE_SIZE, TILE_SIZE)),
-                    source: Some(Rect::new(x, y, TILE_SIZE, TILE_SIZE)),
-                    rotation: tile.rotation as usize as f32 * FRAC_PI_2,
-                    ..Default::default()
-                },
-            )
+            );
         });
     }
 }
 
-#[inline]
-pub fn tile_position_flatten(pos: [usize; 2]) -> usize {
-    pos[0] * MAP_SIZE.0 as usize + pos[1] % MAP_SIZE.0 as usize
+/// Draws a tile of the mapatlas in a rectangle, turned around its center
+pub fn draw_tile(
+    texture: &Texture2D,
+    tile_type: TileType,
+    rotation: Rotation,
+    dest: Rect,
+    color: Color,
+) {
+    let (x, y) = tile_type.mapatlas_source();
+    draw_texture_ex(
+        texture,
+        dest.x,
+        dest.y,
+        color,
+        DrawTextureParams {
+            dest_size: Some(dest.size()),
+            source: Some(Rect::new(x, y, TILE_SIZE, TILE_SIZE)),
+            rotation: rotation as usize as f32 * FRAC_PI_2,
+            ..Default::default()
+        },
+    )
 }
@@ -0,0 +1,76 @@
+use macroquad::{
+    color::{BLACK, WHITE},
+    shapes::{draw_circle, draw_rectangle, draw_rectangle_lines},
+    text::{draw_text_ex, get_text_center, Font, TextParams},
+    time::get_time,
+    window::{clear_background, next_frame, screen_height, screen_width},
+};
+
+use super::{Level, LevelConfig};
+
+/// Draws one frame of the loading screen, `progress` being in [0, 1]
+pub async fn draw_loading_screen(font: &Font, name: &str, progress: f32) {
+    clear_background(BLACK);
+
+    let (sw, sh) = (screen_width(), screen_height());
+
+    let title = format!("Loading {name}");
+    let center = get_text_center(&title, Some(font), 30, 1.0, 0.0);
+    draw_text_ex(
+        &title,
+        sw * 0.5 - center.x,
+        sh * 0.4 - center.y,
+        TextParams {
+            font: Some(font),
+            font_size: 30,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+
+    // progress bar
+    let (w, h) = (sw * 0.4, 20.0);
+    let (x, y) = ((sw - w) * 0.5, sh * 0.5);
+    draw_rectangle_lines(x, y, w, h, 2.0, WHITE);
+    draw_rectangle(x, y, w * progress.clamp(0.0, 1.0), h, WHITE);
+
+    // dots turning under the bar
+    let time = get_time() as f32;
+    for i in 0..3 {
+        let angle = time * 4.0 + i as f32 * 0.6;
+        draw_circle(
+            sw * 0.5 + angle.cos() * 20.0,
+            y + h + 50.0 + angle.sin() * 20.0,
+            4.0,
+            WHITE,
+        );
+    }
+
+    next_frame().await;
+}
+
+/// Levels loaded so far, kept to play them again without loading their assets
+pub struct LevelCache {
+    levels: Vec<Option<Level>>,
+}
+
+impl LevelCache {
+    pub fn new(count: usize) -> Self {
+        Self {
+            levels: (0..count).map(|_| None).collect(),
+        }
+    }
+
+    /// Returns the level, loading it first if it was never played
+    pub async fn get(&mut self, index: usize, conf: &LevelConfig, font: &Font) -> &mut Level {
+        if self.levels[index].is_none() {
+            self.levels[index] = Some(Level::load(conf, font).await);
+        }
+        self.levels[index].as_mut().expect("level loaded above")
+    }
+
+    /// Drops a level whose config changed, to load it again the next time it is played
+    pub fn forget(&mut self, index: usize) {
+        self.levels[index] = None;
+    }
+}
@@ -1,160 +1,311 @@
 use std::f32::consts::LN_2;
 
-use ahash::AHashMap;
-use gilrs::*;
 use macroquad::experimental::animation::*;
 use macroquad::prelude::*;
 
-use crate::game::ZOOM;
-use crate::input::PlayerInput;
+use crate::game::{BoostTrail, ZOOM};
+use crate::input::{Bindings, Device, InputHub, PlayerControls};
+use crate::net::Prediction;
+use crate::save::GhostFrame;
+use crate::sim::{
+    AiDriver, CarStat, Difficulty, PlayerInput, RaceEvent, Racer, RacerState, RacingLine, Track,
+};
 
-use crate::config::CarStat;
+pub const SPRITE_SIZE: (f32, f32) = (32.0, 56.0);
 
-use super::{tile_position_flatten, Collider, Level, LineBorder, RectHitbox, Tile, TILE_SIZE};
+/// Colors the cars of the local players are drawn with
+pub const PLAYER_TINTS: [Color; 4] = [
+    WHITE,
+    Color::new(1.0, 0.6, 0.9, 1.0),
+    Color::new(0.6, 0.9, 1.0, 1.0),
+    Color::new(0.8, 1.0, 0.6, 1.0),
+];
 
-pub const SPRITE_SIZE: (f32, f32) = (32.0, 56.0);
+/// Colors the cars of the computer are drawn with
+pub const OPPONENT_TINTS: [Color; 7] = [
+    Color::new(1.0, 0.5, 0.5, 1.0),
+    Color::new(0.5, 0.7, 1.0, 1.0),
+    Color::new(0.6, 1.0, 0.5, 1.0),
+    Color::new(1.0, 0.9, 0.4, 1.0),
+    Color::new(0.9, 0.5, 1.0, 1.0),
+    Color::new(0.4, 1.0, 0.9, 1.0),
+    Color::new(1.0, 0.7, 0.3, 1.0),
+];
+
+/// Animations of the car: stop, rainbow, death and boost
+pub fn car_sprite() -> AnimatedSprite {
+    AnimatedSprite::new(
+        SPRITE_SIZE.0 as u32,
+        SPRITE_SIZE.1 as u32,
+        &[
+            Animation {
+                name: "stop".to_string(),
+                row: 0,
+                frames: 1,
+                fps: 1,
+            },
+            Animation {
+                name: "rainbow".to_string(),
+                row: 1,
+                frames: 6,
+                fps: 6,
+            },
+            Animation {
+                name: "death".to_string(),
+                row: 2,
+                frames: 1,
+                fps: 1,
+            },
+            Animation {
+                name: "boost".to_string(),
+                row: 1,
+                frames: 6,
+                fps: 18,
+            },
+        ],
+        true,
+    )
+}
+
+/// Loads the sprite sheet shared by every car
+pub async fn car_texture() -> Texture2D {
+    let texture = load_texture("assets/rb_ship.png")
+        .await
+        .expect("car sprite file");
+    texture.set_filter(FilterMode::Nearest);
+    texture
+}
+
+/// What gives the input of a car
+enum Driver {
+    Human(PlayerControls),
+    Ai(AiDriver),
+    /// a player on another computer of the network
+    Remote,
+}
 
 pub struct Player {
     pub sprite: AnimatedSprite,
     pub texture: Texture2D,
 
-    pub position: Vec2,
-    pub rotation: f32,
-    pub velocity: f32,
+    pub racer: Racer,
+
+    // car state interpolated between the last two simulation steps
+    pub render_position: Vec2,
+    pub render_rotation: f32,
 
-    stat: CarStat,
+    pub trail: BoostTrail,
+    /// color the sprite is drawn with
+    pub tint: Color,
 
+    driver: Driver,
     input: PlayerInput,
 }
 
 impl Player {
-    pub async fn new(stat: &CarStat) -> Self {
-        let texture = load_texture("assets/rb_ship.png")
-            .await
-            .expect("car sprite file");
-        texture.set_filter(FilterMode::Nearest);
-
-        Self {
-            sprite: AnimatedSprite::new(
-                SPRITE_SIZE.0 as u32,
-                SPRITE_SIZE.1 as u32,
-                &[
-                    Animation {
-                        name: "stop".to_string(),
-                        row: 0,
-                        frames: 1,
-                        fps: 1,
-                    },
-                    Animation {
-                        name: "rainbow".to_string(),
-                        row: 1,
-                        frames: 6,
-                        fps: 6,
-                    },
-                    Animation {
-                        name: "death".to_string(),
-                        row: 2,
-                        frames: 1,
-                        fps: 1,
-                    },
-                ],
-                true,
-            ),
+    /// Car of a local player reading its own device, placed on a slot of the starting grid.
+    /// The slot 0 is the starting position of the track.
+    pub fn new(
+        texture: &Texture2D,
+        stat: &CarStat,
+        bindings: &Bindings,
+        device: Option<Device>,
+        track: &Track,
+        slot: usize,
+    ) -> Self {
+        let mut player = Self::with_driver(
+            texture.clone(),
+            Racer::on_grid(*stat, track, slot),
+            Driver::Human(PlayerControls::new(bindings, device)),
+        );
+        player.tint = PLAYER_TINTS[slot % PLAYER_TINTS.len()];
+        player
+    }
+
+    /// Car driven by the computer, placed on a slot of the starting grid
+    pub fn opponent(
+        texture: &Texture2D,
+        stat: &CarStat,
+        track: &Track,
+        slot: usize,
+        difficulty: Difficulty,
+    ) -> Self {
+        Self::with_driver(
+            texture.clone(),
+            Racer::on_grid(*stat, track, slot),
+            Driver::Ai(AiDriver::new(difficulty)),
+        )
+    }
+
+    /// Car of a player on another computer, placed on a slot of the starting grid
+    pub fn remote(texture: &Texture2D, stat: &CarStat, track: &Track, slot: usize) -> Self {
+        let mut player = Self::with_driver(
+            texture.clone(),
+            Racer::on_grid(*stat, track, slot),
+            Driver::Remote,
+        );
+        player.tint = PLAYER_TINTS[slot % PLAYER_TINTS.len()];
+        player.sprite.set_animation(0);
+        player
+    }
+
+    fn with_driver(texture: Texture2D, racer: Racer, driver: Driver) -> Self {
+        let mut player = Self {
+            sprite: car_sprite(),
             texture,
-            position: Vec2::new(0.0, 0.0),
-            rotation: 0.0,
-            velocity: 0.0,
-            stat: *stat,
+            racer,
+            render_position: Vec2::new(0.0, 0.0),
+            render_rotation: 0.0,
+            trail: BoostTrail::default(),
+            tint: WHITE,
+            driver,
             input: PlayerInput::default(),
-        }
+        };
+        player.interpolate(1.0);
+        player
     }
 
-    pub fn movement(&mut self, gilrs: &mut Gilrs) {
-        let delta_time = get_frame_time();
-
-        while let Some(Event { event, .. }) = gilrs.next_event() {
-            match event {
-                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
-                    if value > self.input.deadzone || value < -self.input.deadzone {
-                        self.input.accelerate = Some(value);
-                    } else {
-                        self.input.accelerate = None;
-                    }
-                }
-
-                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
-                    if value > self.input.deadzone || value < -self.input.deadzone {
-                        self.input.brake = Some(value);
-                    } else {
-                        self.input.brake = None;
-                    }
-                }
-                EventType::AxisChanged(Axis::LeftStickX, value, _) => {
-                    if value > self.input.deadzone || value < -self.input.deadzone {
-                        self.input.turn = Some(value);
-                    } else {
-                        self.input.turn = None;
-                    }
-                }
-                _ => {}
-            }
+    pub fn handle_input(&mut self, hub: &InputHub) {
+        if let Driver::Human(controls) = &mut self.driver {
+            self.input = controls.update(hub);
         }
+    }
 
-        if self.input.accelerate.is_some() {
-            self.velocity += self.stat.acceleration * self.input.accelerate.unwrap() * delta_time;
-            self.sprite.set_animation(1);
+    /// Lets the computer choose the input of the next simulation step
+    pub fn drive(&mut self, racing_line: &RacingLine, delta_time: f64) {
+        if let Driver::Ai(ai) = &mut self.driver {
+            self.input = ai.input(&self.racer.car, racing_line, delta_time as f32);
         }
-        if self.input.brake.is_some() {
-            self.velocity -= self.input.brake.unwrap() * self.stat.brake * delta_time;
-            self.sprite.set_animation(0);
+    }
+
+    /// The pause action was pressed during the last `handle_input`
+    pub fn pause_pressed(&self) -> bool {
+        matches!(&self.driver, Driver::Human(controls) if controls.pause)
+    }
+
+    /// The gamepad of the player was disconnected during the last `handle_input`
+    pub fn gamepad_disconnected(&self) -> bool {
+        matches!(&self.driver, Driver::Human(controls) if controls.disconnected)
+    }
+
+    /// Device read by the controls of a local player
+    pub fn device(&self) -> Option<Device> {
+        match &self.driver {
+            Driver::Human(controls) => controls.device(),
+            Driver::Ai(_) | Driver::Remote => None,
         }
-        if self.input.turn.is_some() {
-            self.rotation += self.input.turn.unwrap() * self.stat.rotation_speed * delta_time;
+    }
+
+    pub fn set_device(&mut self, device: Option<Device>) {
+        if let Driver::Human(controls) = &mut self.driver {
+            controls.set_device(device);
         }
+    }
 
-        // keyboard controls
-        if is_key_down(KeyCode::Right) | is_key_down(KeyCode::D) {
-            self.rotation += self.stat.rotation_speed * delta_time;
+    /// Reads the controls with new bindings
+    pub fn set_bindings(&mut self, bindings: &Bindings) {
+        if let Driver::Human(controls) = &mut self.driver {
+            controls.set_bindings(bindings);
         }
-        if is_key_down(KeyCode::Left) | is_key_down(KeyCode::Q) | is_key_down(KeyCode::A) {
-            self.rotation -= self.stat.rotation_speed * delta_time;
+    }
+
+    /// Input used by the next simulation step
+    pub fn input(&self) -> PlayerInput {
+        self.input
+    }
+
+    /// Replaces the input read from the controls, to replay a race
+    pub fn set_input(&mut self, input: PlayerInput) {
+        self.input = input;
+    }
+
+    /// Advances the race of the car by one simulation step
+    pub fn update(&mut self, track: &Track, delta_time: f64) -> Option<RaceEvent> {
+        let event = self.racer.step(&self.input, track, delta_time);
+        self.update_animation();
+        event
+    }
+
+    /// Advances the car by one simulation step before the host of the race does it
+    pub fn predict(
+        &mut self,
+        prediction: &mut Prediction,
+        track: &Track,
+        delta_time: f64,
+    ) -> Option<RaceEvent> {
+        let event = prediction.step(&mut self.racer, self.input, track, delta_time);
+        self.update_animation();
+        event
+    }
+
+    /// Moves the car to the state given by the host of the race
+    pub fn follow(&mut self, state: &RacerState) {
+        self.racer.follow(state);
+        self.input = PlayerInput {
+            accelerate: (state.velocity > 0.0) as u8 as f32,
+            ..Default::default()
+        };
+        self.update_animation();
+    }
+
+    fn update_animation(&mut self) {
+        // the particles would stay where the car crashed
+        if self.racer.respawn.is_some() {
+            self.trail.clear();
         }
-        if is_key_down(KeyCode::Down) | is_key_down(KeyCode::S) {
-            self.velocity -= self.stat.brake * delta_time;
+
+        let car = &self.racer.car;
+        if car.crashed {
+            self.sprite.set_animation(2);
+        } else if self.racer.respawn.is_some() {
             self.sprite.set_animation(0);
-        }
-        if is_key_down(KeyCode::Up) | is_key_down(KeyCode::Z) | is_key_down(KeyCode::W) {
-            self.velocity += self.stat.acceleration * delta_time;
+        } else if car.boosting {
+            self.sprite.set_animation(3);
+        } else if self.input.brake > 0.0 {
+            self.sprite.set_animation(0);
+        } else if self.input.accelerate > 0.0 {
             self.sprite.set_animation(1);
         }
+    }
 
-        // Avoid velocity to get higher than max speed
-        self.velocity = self.velocity.clamp(0.0, self.stat.max_velocity);
-        // Move car
-        self.position.x += self.rotation.sin() * self.velocity;
-        self.position.y += -self.rotation.cos() * self.velocity;
-        // Decelerate car
-        self.velocity *= 0.98;
+    /// Places the car between the last two simulation steps, `alpha` being in [0, 1]
+    pub fn interpolate(&mut self, alpha: f32) {
+        (self.render_position, self.render_rotation) = self.racer.car.interpolate(alpha);
     }
 
-    pub fn update(&mut self, gilrs: &mut Gilrs, level: &Level) {
-        if self.collides(&level.tiles) {
-            self.sprite.set_animation(2);
-        } else {
-            self.movement(gilrs);
+    /// State of the car after the last simulation step, to replay it later
+    pub fn ghost_frame(&self) -> GhostFrame {
+        GhostFrame {
+            position: (self.racer.car.position.x, self.racer.car.position.y),
+            rotation: self.racer.car.rotation,
+            animation: self.sprite.current_animation(),
         }
     }
 
+    /// Updates the sprite animation and the boost particles
+    pub fn animate(&mut self, delta_time: f32) {
+        self.sprite.update();
+        let rear = self.render_position
+            - vec2(self.render_rotation.sin(), -self.render_rotation.cos()) * SPRITE_SIZE.1 * 0.5;
+        self.trail.update(&self.racer.car, rear, delta_time);
+    }
+
     pub fn draw(&mut self) {
+        self.trail.draw();
+        // blink while invulnerable
+        let mut color = self.tint;
+        if (self.racer.car.invulnerable * 8.0) as usize % 2 == 1 {
+            color.a = 0.4;
+        }
         draw_texture_ex(
             &self.texture,
-            self.position.x - SPRITE_SIZE.0 * 0.5,
-            self.position.y - SPRITE_SIZE.1 * 0.5,
-            WHITE,
+            self.render_position.x - SPRITE_SIZE.0 * 0.5,
+            self.render_position.y - SPRITE_SIZE.1 * 0.5,
+            color,
             DrawTextureParams {
                 source: Some(self.sprite.frame().source_rect),
                 dest_size: Some(vec2(SPRITE_SIZE.0, SPRITE_SIZE.1)),
-                rotation: self.rotation,
+                rotation: self.render_rotation,
                 ..Default::default()
             },
         )
@@ -162,64 +313,10 @@ impl Player {
 
     // allow to dezoom when the car is fast
     pub fn zoom_speed(&self) -> f32 {
-        ZOOM * ((-LN_2 / (self.stat.max_velocity * self.stat.max_velocity))
-            * self.velocity
-            * self.velocity)
+        let max_velocity = self.racer.car.stat.max_velocity;
+        ZOOM * ((-LN_2 / (max_velocity * max_velocity))
+            * self.racer.car.velocity
+            * self.racer.car.velocity)
             .exp()
     }
-
-    pub fn init(&mut self, pos: [usize; 2]) {
-        self.position = vec2(
-            pos[0] as f32 * TILE_SIZE + TILE_SIZE * 0.5,
-            pos[1] as f32 * TILE_SIZE + TILE_SIZE * 0.5,
-        );
-        self.rotation = 0.0;
-        self.velocity = 0.0;
-    }
-}
-
-impl RectHitbox for Player {
-    fn rect(&self) -> Rect {
-        Rect::new(
-            self.position.x,
-            self.position.y,
-            self.stat.hitbox_size.0,
-            self.stat.hitbox_size.1,
-        )
-    }
-
-    fn rotation(&self) -> f32 {
-        self.rotation
-    }
-}
-
-// impl Collider<LineBorder> for Player {
-//     fn collides(&self, other: LineBorder) -> bool {
-//         let start = other.rotated_start();
-//         // check if player hitbox has a point at the right of the line
-//         self.rotated_points(other.rotation).iter().any(|point| {
-//             point.x > start.x && point.y < start.y && point.y > (start.y - other.lengh)
-//         })
-//     }
-// }
-
-impl Collider<&AHashMap<usize, Tile>> for Player {
-    fn collides(&self, other: &AHashMap<usize, Tile>) -> bool {
-        self.points().iter().any(|point| {
-            other
-                .get(&tile_position_flatten([
-                    (point.x / TILE_SIZE) as usize,
-                    (point.y / TILE_SIZE) as usize,
-                ]))
-                .is_some_and(|tile| {
-                    LineBorder::can_from(tile).is_some_and(|border| point.collides(border))
-                })
-                || other
-                    .get(&tile_position_flatten([
-                        (point.x / TILE_SIZE) as usize,
-                        (point.y / TILE_SIZE) as usize,
-                    ]))
-                    .is_none()
-        })
-    }
 }
@@ -0,0 +1,22 @@
+use macroquad::{color::Color, shapes::draw_rectangle};
+
+use super::Viewport;
+use crate::sim::RESPAWN_DELAY;
+
+/// Fades the viewport of the car to black while it is put back on the track,
+/// `elapsed` being the time since the car started to respawn
+pub fn draw_respawn_fade(elapsed: f32, viewport: Viewport) {
+    let (x, y, w, h) = viewport;
+    let opacity = if elapsed < RESPAWN_DELAY {
+        elapsed / RESPAWN_DELAY
+    } else {
+        (2.0 - elapsed / RESPAWN_DELAY).max(0.0)
+    };
+    draw_rectangle(
+        x as f32,
+        y as f32,
+        w as f32,
+        h as f32,
+        Color::new(0.0, 0.0, 0.0, opacity),
+    );
+}
@@ -1,4 +1,9 @@
-use macroquad::audio::{play_sound, stop_sound, PlaySoundParams, Sound};
+use macroquad::audio::{play_sound, set_sound_volume, stop_sound, PlaySoundParams, Sound};
+use macroquad::experimental::coroutines::{start_coroutine, Coroutine};
+use macroquad::time::get_frame_time;
+use macroquad::window::next_frame;
+
+const CROSSFADE_DURATION: f32 = 1.5;
 
 #[derive(Clone)]
 pub struct MusicParams {
@@ -23,3 +28,40 @@ pub fn play_music(params: &mut MusicParams) {
         params.is_playing = false;
     }
 }
+
+/// Fades `from` out while `to` fades in, in the background of the next frames.
+/// `to` only starts if the music is activated.
+pub fn crossfade(from: &mut MusicParams, to: &mut MusicParams) -> Coroutine {
+    let fade_out = from.is_playing.then(|| (from.sound.clone(), from.volume));
+    from.is_playing = false;
+
+    let fade_in = (!to.is_playing && to.is_activated).then(|| (to.sound.clone(), to.volume));
+    if let Some((sound, _)) = &fade_in {
+        play_sound(
+            sound,
+            PlaySoundParams {
+                looped: true,
+                volume: 0.0,
+            },
+        );
+        to.is_playing = true;
+    }
+
+    start_coroutine(async move {
+        let mut elapsed = 0.0;
+        while elapsed < CROSSFADE_DURATION {
+            elapsed += get_frame_time();
+            let t = (elapsed / CROSSFADE_DURATION).min(1.0);
+            if let Some((sound, volume)) = &fade_out {
+                set_sound_volume(sound, volume * (1.0 - t));
+            }
+            if let Some((sound, volume)) = &fade_in {
+                set_sound_volume(sound, volume * t);
+            }
+            next_frame().await;
+        }
+        if let Some((sound, _)) = fade_out {
+            stop_sound(&sound);
+        }
+    })
+}
@@ -0,0 +1,61 @@
+use super::*;
+use crate::sim::{CheckpointTracker, Crossing};
+
+#[test]
+fn shipped_levels_with_laps_have_checkpoints_in_racing_order() {
+    let levels = std::fs::read_to_string("assets/levels/levels.ron").unwrap();
+    let levels: Levels = ron::from_str(&levels).unwrap();
+    for level in &levels.levels {
+        let track = level.track();
+        // a level without starting line is driven freely, without laps
+        if track.start_line().is_none() {
+            continue;
+        }
+        assert!(
+            level_has_written_checkpoints(level),
+            "{} has no checkpoints in levels.ron",
+            level.name()
+        );
+
+        // a lap of the racing line, from one forward crossing of the starting line to the next
+        let start_line = track.start_line().unwrap();
+        let line = level.racing_line(&track);
+        let points: Vec<_> = line
+            .points
+            .iter()
+            .chain(&line.points)
+            .chain(&line.points)
+            .collect();
+        let mut tracker: Option<CheckpointTracker> = None;
+        let mut lap_completed = false;
+        'walk: for pair in points.windows(2) {
+            let (from, to) = (*pair[0], *pair[1]);
+            // small steps, for the points to land in the tile gates
+            let steps = (from.distance(to) / 2.0).ceil().max(1.0) as usize;
+            for step in 0..steps {
+                let a = from.lerp(to, step as f32 / steps as f32);
+                let b = from.lerp(to, (step + 1) as f32 / steps as f32);
+                if start_line.crossing(a, b) == Some(Crossing::Forward) {
+                    if let Some(tracker) = &tracker {
+                        lap_completed = tracker.completed();
+                        break 'walk;
+                    }
+                    tracker = Some(CheckpointTracker::new(&track.checkpoints));
+                }
+                if let Some(tracker) = &mut tracker {
+                    tracker.update(a, b, 0.0);
+                }
+            }
+        }
+        assert!(
+            lap_completed,
+            "{} checkpoints are not passed in order",
+            level.name()
+        );
+    }
+}
+
+// the checkpoints are only serialized when the level has some
+fn level_has_written_checkpoints(level: &LevelConfig) -> bool {
+    ron::to_string(level).unwrap().contains("checkpoints:")
+}
@@ -1,11 +1,15 @@
 use macroquad::{
     color::WHITE,
     text::{draw_text_ex, Font, TextParams},
-    texture::Texture2D,
-    time::get_frame_time,
-    window::{clear_background, screen_height, screen_width},
+    window::{screen_height, screen_width},
 };
 
+/// Difference with a record, if there is one
+pub fn delta(time: f64, best: Option<f64>) -> String {
+    best.map(|best| format!(" ({:+.2})", time - best))
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub struct Timer {
     elapsed: f64,
@@ -25,19 +29,41 @@ impl Timer {
     pub fn is_done(&self) -> bool {
         self.wait <= self.elapsed
     }
+}
+
+// avoid running too many steps after a freeze of the window
+const MAX_FRAME_TIME: f64 = 0.25;
+
+/// Accumulates the frame time to run the simulation at a fixed rate
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f64,
+    accumulator: f64,
+}
 
-    pub fn reset(&mut self) {
-        while self.is_done() {
-            self.elapsed -= self.wait;
+impl FixedTimestep {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            step: 1.0 / rate,
+            accumulator: 0.0,
         }
     }
 
-    pub fn elapsed(&self) -> f64 {
-        self.elapsed
+    pub fn step(&self) -> f64 {
+        self.step
     }
 
-    pub fn time_left(&self) -> f64 {
-        self.wait - self.elapsed
+    /// Returns the number of simulation steps to run for this frame.
+    pub fn update(&mut self, frame_time: f64) -> usize {
+        self.accumulator += frame_time.min(MAX_FRAME_TIME);
+        let steps = (self.accumulator / self.step) as usize;
+        self.accumulator -= steps as f64 * self.step;
+        steps
+    }
+
+    /// Progression between the last step and the next one
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step) as f32
     }
 }
 
@@ -65,6 +91,11 @@ impl Countdown {
         (self.start - self.count).max(0.0)
     }
 
+    /// Follows a countdown running on another computer
+    pub fn sync(&mut self, time_left: f64) {
+        self.count = (self.start - time_left).max(0.0);
+    }
+
     pub fn draw(&self, font: &Font) {
         if !self.finished() {
             draw_text_ex(
@@ -1,13 +1,25 @@
 mod button;
+mod controls;
 mod credits;
+mod lobby;
 mod main_menu;
 mod options;
+mod pause;
+mod results;
+mod select_level;
 mod style;
+mod toast;
 
+pub use controls::controls;
 pub use credits::credits;
+pub use lobby::lobby;
 pub use main_menu::main_menu;
 pub use options::options;
+pub use pause::{PauseAction, PauseMenu};
+pub use results::{results, PlayerResult, RaceResults};
+pub use select_level::select_level;
 pub use style::GuiResources;
+pub use toast::draw_toast;
 
 pub const BUTTON_SIZE: (f32, f32) = (600.0, 100.0);
 pub const TITLE_FONT_SIZE: u16 = 45;
@@ -1,9 +1,5 @@
-use gilrs::Button as GPButton;
-use gilrs::{Axis, Event, EventType, Gilrs};
-use macroquad::input::is_key_pressed;
 use macroquad::{
     color::WHITE,
-    input::KeyCode,
     math::{vec2, Rect},
     text::{draw_text_ex, get_text_center, TextParams},
     texture::{draw_texture_ex, DrawTextureParams},
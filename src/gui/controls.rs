@@ -0,0 +1,159 @@
+use gilrs::{Event, EventType};
+use macroquad::{
+    color::{BLACK, WHITE},
+    input::{get_last_key_pressed, KeyCode},
+    math::Rect,
+    miniquad::window::screen_size,
+    text::{draw_text_ex, get_text_center, TextParams},
+    window::{clear_background, next_frame},
+};
+
+use crate::gui::style::GuiResources;
+use crate::{
+    game::GameState,
+    input::{Action, Binding, Bindings, InputHub, MenuInput},
+};
+
+use super::{
+    button::{Button, Ui},
+    draw_toast, TITLE_FONT_SIZE,
+};
+
+// a stick has to be pushed that far to be bound
+const AXIS_THRESHOLD: f32 = 0.7;
+
+/// First key or gamepad input pressed since the last frame
+fn capture(hub: &InputHub) -> Option<Binding> {
+    let mut binding = get_last_key_pressed().map(Binding::Key);
+    for Event { event, .. } in hub.events() {
+        match *event {
+            EventType::ButtonPressed(button, _) => {
+                binding = binding.or(Some(Binding::Button(button)));
+            }
+            EventType::AxisChanged(axis, value, _) if value.abs() > AXIS_THRESHOLD => {
+                binding = binding.or(Some(Binding::Axis {
+                    axis,
+                    positive: value > 0.0,
+                }));
+            }
+            _ => {}
+        }
+    }
+    binding
+}
+
+pub async fn controls(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &mut Bindings,
+) -> GameState {
+    let mut ui = Ui::default();
+    let mut input = MenuInput::new();
+    // action waiting for a new input
+    let mut waiting: Option<Action> = None;
+
+    loop {
+        clear_background(BLACK);
+
+        hub.poll();
+        if let Some(action) = waiting {
+            match capture(hub) {
+                Some(Binding::Key(KeyCode::Escape)) => waiting = None,
+                Some(binding) => {
+                    bindings.rebind(action, binding);
+                    bindings.save();
+                    waiting = None;
+                }
+                None => {}
+            }
+        }
+        // still updated while waiting, so the input just bound is not seen as a new press
+        input.update(hub, bindings);
+
+        let (sw, sh) = screen_size();
+
+        // draw title
+        let title = match waiting {
+            Some(action) => format!("Press an input for {}", action.name()),
+            None => "Controls".to_string(),
+        };
+        let font_size = if waiting.is_some() {
+            TITLE_FONT_SIZE / 2
+        } else {
+            TITLE_FONT_SIZE
+        };
+        let text_size = get_text_center(&title, Some(&resources.font), font_size, 1.0, 0.0);
+
+        draw_text_ex(
+            &title,
+            sw * 0.5 - text_size.x,
+            sh * 0.08 + text_size.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        // buttons
+        let button_h = sh * 0.055;
+        let button_w = sw * 0.8;
+        let button_align_x = (sw - button_w) * 0.5;
+        let button_align_y = sh * 0.15;
+        let button_rect = |i: usize| {
+            Rect::new(
+                button_align_x,
+                button_align_y + i as f32 * sh * 0.062,
+                button_w,
+                button_h,
+            )
+        };
+
+        let mut buttons: Vec<Button> = Action::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, action)| {
+                let inputs: Vec<String> = bindings.get(*action).iter().map(Binding::name).collect();
+                Button::new(
+                    button_rect(i),
+                    format!("{}: {}", action.name(), inputs.join(", ")),
+                )
+            })
+            .collect();
+        buttons.push(Button::new(
+            button_rect(Action::ALL.len()),
+            "Reset to defaults".into(),
+        ));
+        buttons.push(Button::back_button());
+        ui.build(buttons);
+        if waiting.is_none() {
+            ui.update(input);
+        } else {
+            ui.update(MenuInput::new());
+        }
+        ui.draw(resources);
+        draw_toast(resources, hub);
+
+        if waiting.is_none() {
+            if let Some(index) = ui.widgets[..Action::ALL.len()]
+                .iter()
+                .position(|button| button.is_clicked())
+            {
+                waiting = Some(Action::ALL[index]);
+            }
+
+            if ui.widgets[Action::ALL.len()].is_clicked() {
+                *bindings = Bindings::shipped();
+                bindings.save();
+            }
+
+            // Back
+            if ui.widgets[Action::ALL.len() + 1].is_clicked() || input.back {
+                return GameState::Options;
+            }
+        }
+
+        next_frame().await;
+    }
+}
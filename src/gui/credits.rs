@@ -1,4 +1,3 @@
-use gilrs::Gilrs;
 use macroquad::{
     color::{BLACK, WHITE},
     miniquad::window::screen_size,
@@ -7,21 +6,29 @@ use macroquad::{
 };
 
 use crate::gui::style::GuiResources;
-use crate::{game::GameState, input::MenuInput};
+use crate::{
+    game::GameState,
+    input::{Bindings, InputHub, MenuInput},
+};
 
 use super::{
     button::{Button, Ui},
-    TITLE_FONT_SIZE,
+    draw_toast, TITLE_FONT_SIZE,
 };
 
-pub async fn credits(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState {
+pub async fn credits(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &Bindings,
+) -> GameState {
     let mut ui = Ui::default();
     let mut input = MenuInput::new();
 
     loop {
         clear_background(BLACK);
 
-        input.update(gilrs);
+        hub.poll();
+        input.update(hub, bindings);
 
         let (sw, sh) = screen_size();
 
@@ -43,6 +50,7 @@ pub async fn credits(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState {
         ui.build(vec![Button::back_button()]);
         ui.update(input);
         ui.draw(resources);
+        draw_toast(resources, hub);
 
         if ui.widgets[0].is_clicked() || input.back {
             return GameState::Menu;
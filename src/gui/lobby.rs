@@ -0,0 +1,103 @@
+use macroquad::{
+    color::{BLACK, WHITE},
+    miniquad::window::screen_size,
+    text::{draw_text_ex, get_text_center, TextParams},
+    time::get_time,
+    window::{clear_background, next_frame},
+};
+
+use crate::gui::style::GuiResources;
+use crate::{
+    game::{GameMode, GameState, LevelConfig},
+    input::{Bindings, InputHub, MenuInput},
+    net::Client,
+};
+
+use super::{
+    button::{Button, Ui},
+    draw_toast, TITLE_FONT_SIZE,
+};
+
+// seconds between two requests to join the host
+const JOIN_INTERVAL: f64 = 1.0;
+
+/// Waits for the host of the local network to open its next race,
+/// returns the level to play or the menu if the player gives up
+pub async fn lobby(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &Bindings,
+    client: &mut Client,
+    levels: &[LevelConfig],
+) -> GameState {
+    let mut ui = Ui::default();
+    let mut input = MenuInput::new();
+    // the lobby of the last race is over
+    client.lobby = None;
+    let mut last_join = get_time();
+    client.join();
+
+    loop {
+        clear_background(BLACK);
+
+        hub.poll();
+        input.update(hub, bindings);
+        client.receive();
+
+        if get_time() - last_join > JOIN_INTERVAL {
+            client.join();
+            last_join = get_time();
+        }
+
+        let status = match &client.lobby {
+            Some(lobby) => match levels.iter().position(|level| level.name() == lobby.level) {
+                Some(index) => return GameState::Playing(GameMode::Online, index),
+                None => format!("The host races on {}, a level missing here", lobby.level),
+            },
+            None if client.disconnected() => "No answer from the host".to_string(),
+            None if client.busy => "A race is running, waiting for the next one".to_string(),
+            None => "Waiting for the host".to_string(),
+        };
+
+        let (sw, sh) = screen_size();
+
+        let title = "Joining a race";
+        let text_size = get_text_center(title, Some(&resources.font), TITLE_FONT_SIZE, 1.0, 0.0);
+
+        draw_text_ex(
+            title,
+            sw * 0.5 - text_size.x,
+            sh * 0.2 + text_size.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size: TITLE_FONT_SIZE,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        let center = get_text_center(&status, Some(&resources.font), 20, 1.0, 0.0);
+        draw_text_ex(
+            &status,
+            sw * 0.5 - center.x,
+            sh * 0.4 - center.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size: 20,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        ui.build(vec![Button::back_button()]);
+        ui.update(input);
+        ui.draw(resources);
+        draw_toast(resources, hub);
+
+        if ui.widgets[0].is_clicked() || input.back {
+            return GameState::Menu;
+        }
+
+        next_frame().await;
+    }
+}
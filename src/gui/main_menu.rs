@@ -1,4 +1,3 @@
-use gilrs::Gilrs;
 use macroquad::{
     color::BLACK,
     math::Rect,
@@ -8,13 +7,20 @@ use macroquad::{
 
 use crate::gui::style::GuiResources;
 use crate::{
-    game::{GameMode, GameState},
-    input::MenuInput,
+    game::GameState,
+    input::{Bindings, InputHub, MenuInput},
 };
 
-use super::button::{Button, Ui};
+use super::{
+    button::{Button, Ui},
+    draw_toast,
+};
 
-pub async fn main_menu(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState {
+pub async fn main_menu(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &Bindings,
+) -> GameState {
     let mut ui = Ui::default();
     let mut input = MenuInput::new();
 
@@ -23,25 +29,42 @@ pub async fn main_menu(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState
 
         let (sw, sh) = screen_size();
 
-        input.update(gilrs);
+        hub.poll();
+        input.update(hub, bindings);
 
         let button_h = (3.0 * sh) / 32.0; // button is 3/4 of 1/2 screen and there are 4 buttons so sh/2/4 * 3/4
         let button_w = 6.0 * button_h;
         let button_align_x = (sw - button_w) / 2.0;
+        // the fifth button starts the menu higher
+        let button_align_y = 3.0 * sh / 8.0;
 
         ui.build(vec![
             Button::new(
-                Rect::new(button_align_x, sh / 2.0, button_w, button_h),
+                Rect::new(button_align_x, button_align_y, button_w, button_h),
                 "Play".into(),
             ),
             Button::new(
-                Rect::new(button_align_x, sh / 2.0 + (sh / 8.0), button_w, button_h),
+                Rect::new(
+                    button_align_x,
+                    button_align_y + (sh / 8.0),
+                    button_w,
+                    button_h,
+                ),
+                "Editor".into(),
+            ),
+            Button::new(
+                Rect::new(
+                    button_align_x,
+                    button_align_y + (2.0 * (sh / 8.0)),
+                    button_w,
+                    button_h,
+                ),
                 "Options".into(),
             ),
             Button::new(
                 Rect::new(
                     button_align_x,
-                    sh / 2.0 + (2.0 * (sh / 8.0)),
+                    button_align_y + (3.0 * (sh / 8.0)),
                     button_w,
                     button_h,
                 ),
@@ -50,7 +73,7 @@ pub async fn main_menu(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState
             Button::new(
                 Rect::new(
                     button_align_x,
-                    sh / 2.0 + (3.0 * (sh / 8.0)),
+                    button_align_y + (4.0 * (sh / 8.0)),
                     button_w,
                     button_h,
                 ),
@@ -59,20 +82,25 @@ pub async fn main_menu(resources: &GuiResources, gilrs: &mut Gilrs) -> GameState
         ]);
         ui.update(input);
         ui.draw(resources);
+        draw_toast(resources, hub);
 
         if ui.widgets[0].is_clicked() {
-            return GameState::Playing(GameMode::Arcade);
+            return GameState::SelectLevel;
         }
 
         if ui.widgets[1].is_clicked() {
-            return GameState::Options;
+            return GameState::Editor(None);
         }
 
         if ui.widgets[2].is_clicked() {
-            return GameState::Credits;
+            return GameState::Options;
         }
 
         if ui.widgets[3].is_clicked() {
+            return GameState::Credits;
+        }
+
+        if ui.widgets[4].is_clicked() {
             return GameState::Quit;
         }
 
@@ -1,4 +1,3 @@
-use gilrs::Gilrs;
 use macroquad::{
     color::{BLACK, WHITE},
     math::Rect,
@@ -9,19 +8,23 @@ use macroquad::{
 
 use crate::gui::style::GuiResources;
 use crate::{
+    config::RaceSettings,
     game::{GameState, MusicParams},
-    input::MenuInput,
+    input::{Bindings, InputHub, MenuInput},
+    sim::Difficulty,
 };
 
 use super::{
     button::{Button, Ui},
-    TITLE_FONT_SIZE,
+    draw_toast, TITLE_FONT_SIZE,
 };
 
 pub async fn options(
     resources: &GuiResources,
-    gilrs: &mut Gilrs,
+    hub: &mut InputHub,
+    bindings: &Bindings,
     music_params: &mut MusicParams,
+    race: &mut RaceSettings,
 ) -> GameState {
     let mut ui = Ui::default();
     let mut input = MenuInput::new();
@@ -29,7 +32,8 @@ pub async fn options(
     loop {
         clear_background(BLACK);
 
-        input.update(gilrs);
+        hub.poll();
+        input.update(hub, bindings);
 
         let (sw, sh) = screen_size();
 
@@ -64,18 +68,75 @@ pub async fn options(
                     "turn music on".into()
                 },
             ),
+            Button::new(
+                Rect::new(
+                    button_align_x,
+                    button_align_y + button_h * 1.2,
+                    button_w,
+                    button_h,
+                ),
+                "controls".into(),
+            ),
+            Button::new(
+                Rect::new(
+                    button_align_x,
+                    button_align_y + button_h * 2.4,
+                    button_w,
+                    button_h,
+                ),
+                format!("opponents: {}", race.opponents),
+            ),
+            Button::new(
+                Rect::new(
+                    button_align_x,
+                    button_align_y + button_h * 3.6,
+                    button_w,
+                    button_h,
+                ),
+                format!("difficulty: {}", race.difficulty.name().to_lowercase()),
+            ),
+            Button::new(
+                Rect::new(
+                    button_align_x,
+                    button_align_y + button_h * 4.8,
+                    button_w,
+                    button_h,
+                ),
+                format!("players: {}", race.players),
+            ),
             Button::back_button(),
         ]);
         ui.update(input);
         ui.draw(resources);
+        draw_toast(resources, hub);
 
         if ui.widgets[0].is_clicked() {
             music_params.is_activated ^= true;
             return GameState::Options;
         }
 
+        if ui.widgets[1].is_clicked() {
+            return GameState::Controls;
+        }
+
+        if ui.widgets[2].is_clicked() {
+            race.opponents = (race.opponents + 1) % (RaceSettings::MAX_OPPONENTS + 1);
+        }
+
+        if ui.widgets[3].is_clicked() {
+            let index = Difficulty::ALL
+                .iter()
+                .position(|difficulty| *difficulty == race.difficulty)
+                .unwrap_or(0);
+            race.difficulty = Difficulty::ALL[(index + 1) % Difficulty::ALL.len()];
+        }
+
+        if ui.widgets[4].is_clicked() {
+            race.players = race.players % RaceSettings::MAX_PLAYERS + 1;
+        }
+
         // Back
-        if ui.widgets[1].is_clicked() || input.back {
+        if ui.widgets[5].is_clicked() || input.back {
             return GameState::Menu;
         }
 
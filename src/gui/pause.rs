@@ -0,0 +1,116 @@
+use macroquad::{
+    color::{Color, ORANGE, WHITE},
+    math::Rect,
+    miniquad::window::screen_size,
+    shapes::draw_rectangle,
+    text::{draw_text_ex, get_text_center, TextParams},
+};
+
+use crate::gui::style::GuiResources;
+use crate::input::MenuInput;
+
+use super::{
+    button::{Button, Ui},
+    TITLE_FONT_SIZE,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseAction {
+    Resume,
+    Restart,
+    Options,
+    Quit,
+}
+
+/// Menu drawn over the race while it is paused
+#[derive(Default)]
+pub struct PauseMenu {
+    ui: Ui,
+    /// shown under the title, to ask for a controller to be reconnected
+    pub prompt: Option<String>,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Draws the menu over the current frame, returns the action chosen by the player
+    pub fn update(&mut self, resources: &GuiResources, input: MenuInput) -> Option<PauseAction> {
+        let (sw, sh) = screen_size();
+
+        draw_rectangle(0.0, 0.0, sw, sh, Color::new(0.0, 0.0, 0.0, 0.6));
+
+        // draw title
+        let title = "Paused";
+        let text_size = get_text_center(title, Some(&resources.font), TITLE_FONT_SIZE, 1.0, 0.0);
+
+        draw_text_ex(
+            title,
+            sw * 0.5 - text_size.x,
+            sh * 0.2 + text_size.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size: TITLE_FONT_SIZE,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        if let Some(prompt) = &self.prompt {
+            let center = get_text_center(prompt, Some(&resources.font), 24, 1.0, 0.0);
+            draw_text_ex(
+                prompt,
+                sw * 0.5 - center.x,
+                sh * 0.26 - center.y,
+                TextParams {
+                    font: Some(&resources.font),
+                    font_size: 24,
+                    color: ORANGE,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // buttons
+        let button_h = 3.0 * sh / 40.0;
+        let button_w = 6.0 * button_h;
+        let button_align_x = (sw - button_w) * 0.5;
+        let button_align_y = sh * 0.3;
+
+        let actions = [
+            (PauseAction::Resume, "Resume"),
+            (PauseAction::Restart, "Restart"),
+            (PauseAction::Options, "Options"),
+            (PauseAction::Quit, "Quit to menu"),
+        ];
+        self.ui.build(
+            actions
+                .iter()
+                .enumerate()
+                .map(|(i, (_, name))| {
+                    Button::new(
+                        Rect::new(
+                            button_align_x,
+                            button_align_y + i as f32 * button_h * 1.2,
+                            button_w,
+                            button_h,
+                        ),
+                        name.to_string(),
+                    )
+                })
+                .collect(),
+        );
+        self.ui.update(input);
+        self.ui.draw(resources);
+
+        if input.back {
+            return Some(PauseAction::Resume);
+        }
+        self.ui
+            .widgets
+            .iter()
+            .position(Button::is_clicked)
+            .map(|index| actions[index].0)
+    }
+}
@@ -0,0 +1,242 @@
+use macroquad::{
+    color::{Color, BLACK, GOLD, WHITE},
+    math::Rect,
+    miniquad::window::screen_size,
+    text::{draw_text_ex, get_text_center, TextParams},
+    window::{clear_background, next_frame},
+};
+
+use crate::gui::style::GuiResources;
+use crate::{
+    game::{delta, GameMode, GameState},
+    input::{Bindings, InputHub, MenuInput},
+    net::Session,
+    save::LevelRecord,
+    sim::Racer,
+};
+
+use super::{
+    button::{Button, Ui},
+    draw_toast, TITLE_FONT_SIZE,
+};
+
+/// Summary of a finished race
+#[derive(Clone, Debug)]
+pub struct RaceResults {
+    pub mode: GameMode,
+    pub level_index: usize,
+    pub level_name: String,
+    pub total: f64,
+    pub lap_times: Vec<f64>,
+    pub crashes: usize,
+    pub respawns: usize,
+    /// rank of the player and number of cars in the race
+    pub position: (usize, usize),
+    /// records of the level before the race
+    pub previous: LevelRecord,
+    /// whether the race could beat the records, which a replay or several players can't
+    pub records: bool,
+    /// result of each local player in the finish order, empty when racing alone
+    pub players: Vec<PlayerResult>,
+}
+
+/// Result of one of the local players of a split-screen race
+#[derive(Clone, Debug)]
+pub struct PlayerResult {
+    /// index of the player, from 0
+    pub player: usize,
+    pub position: usize,
+    pub time: f64,
+    pub best_lap: Option<f64>,
+}
+
+impl RaceResults {
+    pub fn new(
+        mode: GameMode,
+        level_index: usize,
+        level_name: &str,
+        racer: &Racer,
+        position: (usize, usize),
+        previous: LevelRecord,
+        records: bool,
+    ) -> Self {
+        Self {
+            mode,
+            level_index,
+            level_name: level_name.to_string(),
+            total: racer.time,
+            lap_times: racer.race.lap_times.clone(),
+            crashes: racer.crashes,
+            respawns: racer.race.respawns,
+            position,
+            previous,
+            records,
+            players: Vec::new(),
+        }
+    }
+
+    pub fn best_lap(&self) -> Option<f64> {
+        self.lap_times.iter().copied().reduce(f64::min)
+    }
+}
+
+/// Shows the times of the race, returns the next state chosen by the player.
+/// `level_count` is the number of levels, to know if there is a next one.
+/// The race shared with the local network is kept meanwhile.
+pub async fn results(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &Bindings,
+    results: &RaceResults,
+    level_count: usize,
+    mut session: Option<&mut Session>,
+) -> GameState {
+    let mut ui = Ui::default();
+    let mut input = MenuInput::new();
+    let next_level = Some(results.level_index + 1).filter(|index| *index < level_count);
+
+    // each line with its color, new records being highlighted
+    let record_line = |label: &str, time: f64, best: Option<f64>| {
+        if !results.records {
+            (format!("{label}: {time:.2}s"), WHITE)
+        } else if best.is_none_or(|best| time < best) {
+            (
+                format!("{label}: {time:.2}s{}  New record!", delta(time, best)),
+                GOLD,
+            )
+        } else {
+            (format!("{label}: {time:.2}s{}", delta(time, best)), WHITE)
+        }
+    };
+    let mut lines: Vec<(String, Color)> = Vec::new();
+    let (position, racers) = results.position;
+    if racers > 1 && results.players.is_empty() {
+        let color = if position == 1 { GOLD } else { WHITE };
+        lines.push((format!("Position: {position}/{racers}"), color));
+    }
+    for player in &results.players {
+        let color = if player.position == 1 { GOLD } else { WHITE };
+        let best_lap = player.best_lap.map_or_else(String::new, |best_lap| {
+            format!("  Best lap: {best_lap:.2}s")
+        });
+        lines.push((
+            format!(
+                "{}/{racers}  Player {}: {:.2}s{best_lap}",
+                player.position,
+                player.player + 1,
+                player.time
+            ),
+            color,
+        ));
+    }
+    // the details and the records are those of a player racing alone
+    if results.players.is_empty() {
+        lines.push(record_line(
+            "Total",
+            results.total,
+            results.previous.best_time,
+        ));
+        for (i, time) in results.lap_times.iter().enumerate() {
+            lines.push((format!("Lap {}: {time:.2}s", i + 1), WHITE));
+        }
+        if let Some(best_lap) = results.best_lap() {
+            lines.push(record_line("Best lap", best_lap, results.previous.best_lap));
+        }
+        lines.push((
+            format!(
+                "Crashes: {}  Respawns: {}",
+                results.crashes, results.respawns
+            ),
+            WHITE,
+        ));
+    }
+
+    loop {
+        clear_background(BLACK);
+
+        hub.poll();
+        input.update(hub, bindings);
+        if let Some(session) = session.as_deref_mut() {
+            session.keep_alive();
+        }
+
+        let (sw, sh) = screen_size();
+
+        // draw title
+        let title = results.level_name.as_str();
+        let text_size = get_text_center(title, Some(&resources.font), TITLE_FONT_SIZE, 1.0, 0.0);
+
+        draw_text_ex(
+            title,
+            sw * 0.5 - text_size.x,
+            sh * 0.1 + text_size.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size: TITLE_FONT_SIZE,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        for (i, (line, color)) in lines.iter().enumerate() {
+            let center = get_text_center(line, Some(&resources.font), 20, 1.0, 0.0);
+            draw_text_ex(
+                line,
+                sw * 0.5 - center.x,
+                sh * 0.2 + i as f32 * 30.0,
+                TextParams {
+                    font: Some(&resources.font),
+                    font_size: 20,
+                    color: *color,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // buttons
+        let button_h = 3.0 * sh / 40.0;
+        let button_w = 6.0 * button_h;
+        let button_align_x = (sw - button_w) * 0.5;
+        let button_align_y = sh * 0.62;
+        let button_rect = |i: usize| {
+            Rect::new(
+                button_align_x,
+                button_align_y + i as f32 * button_h * 1.2,
+                button_w,
+                button_h,
+            )
+        };
+
+        let mut buttons = vec![Button::new(button_rect(0), "Retry".into())];
+        if next_level.is_some() {
+            buttons.push(Button::new(button_rect(1), "Next level".into()));
+        }
+        buttons.push(Button::new(button_rect(buttons.len()), "Menu".into()));
+        ui.build(buttons);
+        ui.update(input);
+        ui.draw(resources);
+        draw_toast(resources, hub);
+
+        if ui.widgets[0].is_clicked() {
+            return GameState::Playing(results.mode, results.level_index);
+        }
+
+        if let Some(index) = next_level {
+            if ui.widgets[1].is_clicked() {
+                // an online race goes on online
+                let mode = match results.mode {
+                    GameMode::Online => GameMode::Online,
+                    _ => GameMode::Arcade,
+                };
+                return GameState::Playing(mode, index);
+            }
+        }
+
+        // Menu
+        if ui.widgets.last().is_some_and(Button::is_clicked) || input.back {
+            return GameState::Menu;
+        }
+
+        next_frame().await;
+    }
+}
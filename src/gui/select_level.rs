@@ -0,0 +1,147 @@
+use macroquad::{
+    color::{BLACK, DARKGRAY, WHITE},
+    math::Rect,
+    miniquad::window::screen_size,
+    text::{draw_text_ex, get_text_center, TextParams},
+    window::{clear_background, next_frame},
+};
+
+use crate::gui::style::GuiResources;
+use crate::{
+    game::{GameMode, GameState, LevelConfig, Minimap},
+    input::{Bindings, InputHub, MenuInput},
+    net::Session,
+    save::Records,
+};
+
+use super::{
+    button::{Button, Ui},
+    draw_toast, TITLE_FONT_SIZE,
+};
+
+/// Lets the player choose a level, the race shared with the local network being kept meanwhile
+pub async fn select_level(
+    resources: &GuiResources,
+    hub: &mut InputHub,
+    bindings: &Bindings,
+    levels: &[LevelConfig],
+    records: &Records,
+    mut session: Option<&mut Session>,
+) -> GameState {
+    let mut ui = Ui::default();
+    let mut input = MenuInput::new();
+    // map of the selected level, built again when the selection changes
+    let mut thumbnail: Option<(usize, Minimap)> = None;
+
+    loop {
+        clear_background(BLACK);
+
+        hub.poll();
+        input.update(hub, bindings);
+        if let Some(session) = session.as_deref_mut() {
+            session.keep_alive();
+        }
+
+        let (sw, sh) = screen_size();
+
+        // draw title
+        let title = "Select level";
+        let text_size = get_text_center(title, Some(&resources.font), TITLE_FONT_SIZE, 1.0, 0.0);
+
+        draw_text_ex(
+            title,
+            sw * 0.5 - text_size.x,
+            sh * 0.2 + text_size.y,
+            TextParams {
+                font: Some(&resources.font),
+                font_size: TITLE_FONT_SIZE,
+                color: WHITE,
+                ..Default::default()
+            },
+        );
+
+        // one button per level on the left, the selected level on the right
+        let button_h = 3.0 * sh / 40.0;
+        let button_w = 5.0 * button_h;
+        let button_align_x = sw * 0.1;
+        let button_align_y = sh * 0.3;
+
+        let mut buttons: Vec<Button> = levels
+            .iter()
+            .enumerate()
+            .map(|(i, level)| {
+                Button::new(
+                    Rect::new(
+                        button_align_x,
+                        button_align_y + i as f32 * button_h * 1.2,
+                        button_w,
+                        button_h,
+                    ),
+                    level.name().into(),
+                )
+            })
+            .collect();
+        buttons.push(Button::back_button());
+        ui.build(buttons);
+        ui.update(input);
+        ui.draw(resources);
+        draw_toast(resources, hub);
+
+        if let Some(level) = levels.get(ui.cursor) {
+            // the map is twice as wide as it is high
+            let thumbnail_w = (sw * 0.9 - button_align_x - button_w).min(sh);
+            let dest = Rect::new(
+                sw * 0.9 - thumbnail_w,
+                button_align_y,
+                thumbnail_w,
+                thumbnail_w * 0.5,
+            );
+            if thumbnail
+                .as_ref()
+                .is_none_or(|(index, _)| *index != ui.cursor)
+            {
+                thumbnail = Some((ui.cursor, level.thumbnail()));
+            }
+            if let Some((_, minimap)) = &thumbnail {
+                minimap.draw(dest, DARKGRAY);
+            }
+
+            let record = records.level(level.name());
+            let format_time = |time: Option<f64>| match time {
+                Some(time) => format!("{time:.2}s"),
+                None => "--".to_string(),
+            };
+            let lines = [
+                format!("Best: {}", format_time(record.and_then(|r| r.best_time))),
+                format!("Best lap: {}", format_time(record.and_then(|r| r.best_lap))),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                draw_text_ex(
+                    line,
+                    dest.x,
+                    dest.bottom() + 40.0 + i as f32 * 30.0,
+                    TextParams {
+                        font: Some(&resources.font),
+                        font_size: 20,
+                        color: WHITE,
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+
+        if let Some(index) = ui.widgets[..levels.len()]
+            .iter()
+            .position(|button| button.is_clicked())
+        {
+            return GameState::Playing(GameMode::Arcade, index);
+        }
+
+        // Back
+        if ui.widgets[levels.len()].is_clicked() || input.back {
+            return GameState::Menu;
+        }
+
+        next_frame().await;
+    }
+}
@@ -0,0 +1,35 @@
+use macroquad::{
+    color::{Color, WHITE},
+    miniquad::window::screen_size,
+    shapes::draw_rectangle,
+    text::{draw_text_ex, get_text_center, TextParams},
+};
+
+use crate::gui::style::GuiResources;
+use crate::input::InputHub;
+
+const TOAST_FONT_SIZE: u16 = 20;
+
+/// Draws the message about the gamepads at the bottom of the screen, if there is one
+pub fn draw_toast(resources: &GuiResources, hub: &InputHub) {
+    let Some(message) = hub.toast() else {
+        return;
+    };
+    let (sw, sh) = screen_size();
+    let center = get_text_center(message, Some(&resources.font), TOAST_FONT_SIZE, 1.0, 0.0);
+    let (w, h) = (center.x * 2.0 + 40.0, 40.0);
+    let y = sh - 100.0;
+
+    draw_rectangle((sw - w) * 0.5, y, w, h, Color::new(0.0, 0.0, 0.0, 0.7));
+    draw_text_ex(
+        message,
+        sw * 0.5 - center.x,
+        y + h * 0.5 - center.y,
+        TextParams {
+            font: Some(&resources.font),
+            font_size: TOAST_FONT_SIZE,
+            color: WHITE,
+            ..Default::default()
+        },
+    );
+}
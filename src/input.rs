@@ -1,5 +1,16 @@
-use gilrs::{Axis, Button, Event, EventType, Gilrs};
-use macroquad::input::{is_key_pressed, KeyCode};
+use gilrs::GamepadId;
+
+use crate::sim::PlayerInput;
+
+mod bindings;
+mod hub;
+mod keycode;
+
+pub use bindings::*;
+pub use hub::*;
+
+#[cfg(test)]
+mod tests;
 
 #[derive(Clone, Copy, Debug)]
 pub struct MenuInput {
@@ -7,8 +18,8 @@ pub struct MenuInput {
     pub down: bool,
     pub up: bool,
     pub back: bool,
-    deadzone: f32,
-    prev: f32,
+    // actions held during the previous frame, to only react once per press
+    held: [bool; 4],
 }
 
 impl MenuInput {
@@ -18,65 +29,115 @@ impl MenuInput {
             down: false,
             up: false,
             back: false,
-            deadzone: 0.35,
-            prev: 0.0,
+            held: [false; 4],
         }
     }
 
-    pub fn update(&mut self, gilrs: &mut Gilrs) {
-        self.up =
-            is_key_pressed(KeyCode::Z) || is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W);
-        self.down = is_key_pressed(KeyCode::S) || is_key_pressed(KeyCode::Down);
-
-        self.click = is_key_pressed(KeyCode::Enter);
-        self.back = is_key_pressed(KeyCode::Backspace) || is_key_pressed(KeyCode::Escape);
-
-        while let Some(Event { event, .. }) = gilrs.next_event() {
-            match event {
-                EventType::ButtonPressed(Button::South, _) => {
-                    self.click = true;
-                }
-                EventType::ButtonReleased(Button::South, _) => {
-                    self.click = false;
-                }
-                EventType::ButtonPressed(Button::East, _) => {
-                    self.back = true;
-                }
-                EventType::ButtonReleased(Button::East, _) => {
-                    self.back = false;
-                }
-                EventType::AxisChanged(Axis::LeftStickY, value, _) => {
-                    if (value > self.deadzone && value > self.prev)
-                        || (value < self.deadzone && value < self.prev)
-                    {
-                        self.prev = value;
-                    }
-                }
-                _ => {}
-            }
-            self.down = self.prev < -0.985;
-            self.up = self.prev > 0.985;
+    /// Reads the actions of every device, the hub being polled for this frame
+    pub fn update(&mut self, hub: &InputHub, bindings: &Bindings) {
+        let down = [
+            Action::MenuUp,
+            Action::MenuDown,
+            Action::MenuConfirm,
+            Action::MenuBack,
+        ]
+        .map(|action| bindings.is_down(action, hub));
+        let pressed: [bool; 4] = std::array::from_fn(|i| down[i] && !self.held[i]);
+        self.held = down;
+
+        [self.up, self.down, self.click, self.back] = pressed;
+    }
+}
+
+/// What a player controls its car with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Device {
+    Keyboard,
+    Gamepad(GamepadId),
+}
+
+impl Device {
+    /// Devices of `players` local players: the keyboard, then each connected gamepad.
+    /// A single player uses every device, `None`.
+    pub fn assign(hub: &InputHub, players: usize) -> Vec<Option<Device>> {
+        if players <= 1 {
+            return vec![None];
         }
+        std::iter::once(Device::Keyboard)
+            .chain(hub.gamepads().map(|(id, _)| Device::Gamepad(id)))
+            .take(players)
+            .map(Some)
+            .collect()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct PlayerInput {
-    pub accelerate: Option<f32>,
-    pub turn: Option<f32>,
-    pub brake: Option<f32>,
-    pub boost: bool,
-    pub deadzone: f32,
+/// Player given a gamepad plugged in, among the ones `waiting` for theirs:
+/// the one who had this gamepad, else the first one waiting
+pub fn seat_for(gamepad: GamepadId, devices: &[Option<Device>], waiting: &[bool]) -> Option<usize> {
+    let device = Some(Device::Gamepad(gamepad));
+    devices
+        .iter()
+        .zip(waiting)
+        .position(|(other, waiting)| *waiting && *other == device)
+        .or_else(|| waiting.iter().position(|waiting| *waiting))
+}
+
+/// Reads the keyboard and the gamepad to control a car
+#[derive(Debug, Clone)]
+pub struct PlayerControls {
+    bindings: Bindings,
+    /// the only device read, all of them if `None`
+    device: Option<Device>,
+    /// pause was pressed during the last update
+    pub pause: bool,
+    pause_held: bool,
+    /// the gamepad of the player was disconnected during the last update
+    pub disconnected: bool,
 }
 
-impl Default for PlayerInput {
-    fn default() -> Self {
+impl PlayerControls {
+    pub fn new(bindings: &Bindings, device: Option<Device>) -> Self {
         Self {
-            accelerate: None,
-            turn: None,
-            brake: None,
-            boost: false,
-            deadzone: 0.32,
+            bindings: bindings.clone(),
+            device,
+            pause: false,
+            pause_held: false,
+            disconnected: false,
         }
     }
+
+    /// Reads the device of the player, the hub being polled for this frame
+    pub fn update(&mut self, hub: &InputHub) -> PlayerInput {
+        self.disconnected = hub.disconnected().any(|id| match self.device {
+            None => true,
+            Some(device) => device == Device::Gamepad(id),
+        });
+
+        let value = |action| self.bindings.value_on(action, hub, self.device);
+        let pause = value(Action::Pause) > 0.5;
+        self.pause = pause && !self.pause_held;
+        self.pause_held = pause;
+
+        PlayerInput {
+            accelerate: value(Action::Accelerate),
+            brake: value(Action::Brake),
+            turn: value(Action::SteerRight) - value(Action::SteerLeft),
+            boost: value(Action::Boost) > 0.5,
+            reset: value(Action::Reset) > 0.5,
+        }
+    }
+
+    pub fn device(&self) -> Option<Device> {
+        self.device
+    }
+
+    /// Reads another device, when the gamepad of the player was replaced
+    pub fn set_device(&mut self, device: Option<Device>) {
+        self.device = device;
+    }
+
+    /// Reads the same device with new bindings
+    pub fn set_bindings(&mut self, bindings: &Bindings) {
+        self.bindings = bindings.clone();
+    }
 }
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use gilrs::{Axis, Button};
+use macroquad::input::{is_key_down, KeyCode};
+use serde::{Deserialize, Serialize};
+
+use super::{keycode::KeyCodeDef, Device, InputHub};
+use crate::save::data_dir;
+
+/// Controls shipped with the game, the ones of the player are saved with the records
+const DEFAULT_BINDINGS_PATH: &str = "assets/controls.ron";
+const BINDINGS_FILE: &str = "controls.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    Accelerate,
+    Brake,
+    SteerLeft,
+    SteerRight,
+    Boost,
+    Pause,
+    Reset,
+    MenuUp,
+    MenuDown,
+    MenuConfirm,
+    MenuBack,
+}
+
+impl Action {
+    pub const ALL: [Action; 11] = [
+        Action::Accelerate,
+        Action::Brake,
+        Action::SteerLeft,
+        Action::SteerRight,
+        Action::Boost,
+        Action::Pause,
+        Action::Reset,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuConfirm,
+        Action::MenuBack,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Accelerate => "Accelerate",
+            Action::Brake => "Brake",
+            Action::SteerLeft => "Steer left",
+            Action::SteerRight => "Steer right",
+            Action::Boost => "Boost",
+            Action::Pause => "Pause",
+            Action::Reset => "Reset",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::MenuConfirm => "Menu confirm",
+            Action::MenuBack => "Menu back",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(#[serde(with = "KeyCodeDef")] KeyCode),
+    /// analog for the triggers
+    Button(Button),
+    /// one direction of a stick
+    Axis {
+        axis: Axis,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    pub fn is_keyboard(&self) -> bool {
+        matches!(self, Binding::Key(_))
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Button(button) => format!("{button:?}"),
+            Binding::Axis { axis, positive } => {
+                format!("{axis:?}{}", if *positive { "+" } else { "-" })
+            }
+        }
+    }
+
+    // value in [0, 1] of the binding on a device, on all of them if `None`
+    fn value(&self, hub: &InputHub, device: Option<Device>) -> f32 {
+        let gamepads = || {
+            hub.gamepads()
+                .filter(move |(id, _)| match device {
+                    None => true,
+                    Some(Device::Gamepad(gamepad)) => *id == gamepad,
+                    Some(Device::Keyboard) => false,
+                })
+                .map(|(_, gamepad)| gamepad)
+        };
+        match *self {
+            Binding::Key(key) => {
+                let keyboard = matches!(device, None | Some(Device::Keyboard));
+                (keyboard && is_key_down(key)) as u8 as f32
+            }
+            Binding::Button(button) => gamepads()
+                .map(|gamepad| gamepad.button(button))
+                .fold(0.0, f32::max),
+            Binding::Axis { axis, positive } => gamepads()
+                .map(|gamepad| {
+                    let value = gamepad.axis(axis);
+                    if positive {
+                        value
+                    } else {
+                        -value
+                    }
+                })
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+/// Keyboard keys and gamepad inputs bound to each action
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bindings {
+    pub deadzone: f32,
+    pub actions: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let actions = [
+            (
+                Action::Accelerate,
+                vec![
+                    Binding::Key(KeyCode::Up),
+                    Binding::Key(KeyCode::Z),
+                    Binding::Key(KeyCode::W),
+                    Binding::Button(Button::RightTrigger2),
+                ],
+            ),
+            (
+                Action::Brake,
+                vec![
+                    Binding::Key(KeyCode::Down),
+                    Binding::Key(KeyCode::S),
+                    Binding::Button(Button::LeftTrigger2),
+                ],
+            ),
+            (
+                Action::SteerLeft,
+                vec![
+                    Binding::Key(KeyCode::Left),
+                    Binding::Key(KeyCode::Q),
+                    Binding::Key(KeyCode::A),
+                    Binding::Axis {
+                        axis: Axis::LeftStickX,
+                        positive: false,
+                    },
+                ],
+            ),
+            (
+                Action::SteerRight,
+                vec![
+                    Binding::Key(KeyCode::Right),
+                    Binding::Key(KeyCode::D),
+                    Binding::Axis {
+                        axis: Axis::LeftStickX,
+                        positive: true,
+                    },
+                ],
+            ),
+            (
+                Action::Boost,
+                vec![Binding::Key(KeyCode::Space), Binding::Button(Button::West)],
+            ),
+            (
+                Action::Pause,
+                vec![
+                    Binding::Key(KeyCode::Escape),
+                    Binding::Button(Button::Start),
+                ],
+            ),
+            (
+                Action::Reset,
+                vec![Binding::Key(KeyCode::R), Binding::Button(Button::North)],
+            ),
+            (
+                Action::MenuUp,
+                vec![
+                    Binding::Key(KeyCode::Up),
+                    Binding::Key(KeyCode::Z),
+                    Binding::Key(KeyCode::W),
+                    Binding::Button(Button::DPadUp),
+                    Binding::Axis {
+                        axis: Axis::LeftStickY,
+                        positive: true,
+                    },
+                ],
+            ),
+            (
+                Action::MenuDown,
+                vec![
+                    Binding::Key(KeyCode::Down),
+                    Binding::Key(KeyCode::S),
+                    Binding::Button(Button::DPadDown),
+                    Binding::Axis {
+                        axis: Axis::LeftStickY,
+                        positive: false,
+                    },
+                ],
+            ),
+            (
+                Action::MenuConfirm,
+                vec![Binding::Key(KeyCode::Enter), Binding::Button(Button::South)],
+            ),
+            (
+                Action::MenuBack,
+                vec![
+                    Binding::Key(KeyCode::Backspace),
+                    Binding::Key(KeyCode::Escape),
+                    Binding::Button(Button::East),
+                ],
+            ),
+        ];
+
+        Self {
+            deadzone: 0.32,
+            actions: actions.into_iter().collect(),
+        }
+    }
+}
+
+impl Bindings {
+    fn path() -> PathBuf {
+        data_dir().join(BINDINGS_FILE)
+    }
+
+    /// Reads the controls of the player, or falls back to the ones shipped with the game
+    pub fn load() -> Self {
+        Self::load_from(&Self::path()).unwrap_or_else(Self::shipped)
+    }
+
+    /// Controls shipped with the game
+    pub fn shipped() -> Self {
+        Self::load_from(Path::new(DEFAULT_BINDINGS_PATH)).unwrap_or_default()
+    }
+
+    pub(super) fn load_from(path: &Path) -> Option<Self> {
+        let bindings_str = std::fs::read_to_string(path).ok()?;
+        ron::from_str(&bindings_str)
+            .map_err(|err| {
+                eprintln!("[ERR] invalid {}, ignored: {err}", path.display());
+            })
+            .ok()
+    }
+
+    pub fn save(&self) {
+        self.save_to(&Self::path());
+    }
+
+    pub(super) fn save_to(&self, path: &Path) {
+        let bindings_str = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .expect("serializable bindings");
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let result = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(path, bindings_str));
+        if let Err(err) = result {
+            eprintln!("[ERR] could not save {}: {err}", path.display());
+        }
+    }
+
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds the action to a new input in place of its first input of the same device,
+    /// its other inputs are kept
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.actions.entry(action).or_default();
+        bindings.retain(|other| *other != binding);
+        match bindings
+            .iter_mut()
+            .find(|other| other.is_keyboard() == binding.is_keyboard())
+        {
+            Some(edited) => *edited = binding,
+            None => bindings.push(binding),
+        }
+    }
+
+    /// Strength of the action in [0, 1] on any device, once the deadzone is applied
+    pub fn value(&self, action: Action, hub: &InputHub) -> f32 {
+        self.value_on(action, hub, None)
+    }
+
+    /// Strength of the action in [0, 1] on one device, on all of them if `None`
+    pub fn value_on(&self, action: Action, hub: &InputHub, device: Option<Device>) -> f32 {
+        self.get(action)
+            .iter()
+            .map(|binding| binding.value(hub, device))
+            .filter(|value| *value > self.deadzone)
+            .fold(0.0, f32::max)
+            .min(1.0)
+    }
+
+    pub fn is_down(&self, action: Action, hub: &InputHub) -> bool {
+        self.value(action, hub) > 0.5
+    }
+}
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap;
+use gilrs::{Axis, Button, Event, EventType, GamepadId, Gilrs};
+
+// time a message about the gamepads stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+/// Buttons and sticks of a connected gamepad, as its events left them
+#[derive(Clone, Debug, Default)]
+pub struct GamepadState {
+    pub name: String,
+    buttons: AHashMap<Button, f32>,
+    axes: AHashMap<Axis, f32>,
+}
+
+impl GamepadState {
+    /// Value in [0, 1] of a button, analog for the triggers
+    pub fn button(&self, button: Button) -> f32 {
+        self.buttons.get(&button).copied().unwrap_or(0.0)
+    }
+
+    /// Value in [-1, 1] of a stick axis
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes.get(&axis).copied().unwrap_or(0.0)
+    }
+}
+
+/// Polls the gamepads once per frame and keeps the state of each of them,
+/// for the menus and the players to read their own device only
+pub struct InputHub {
+    /// `None` without gamepad support, only the keyboard is read then
+    gilrs: Option<Gilrs>,
+    gamepads: AHashMap<GamepadId, GamepadState>,
+    /// events of the last poll
+    events: Vec<Event>,
+    // message about the last connection change, and when it disappears
+    toast: Option<(String, Instant)>,
+}
+
+impl InputHub {
+    /// Detects the gamepads, falls back to the keyboard if they can't be read
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            // a context without gamepads still works on unsupported platforms
+            Err(gilrs::Error::NotImplemented(gilrs)) => Some(gilrs),
+            Err(err) => {
+                eprintln!("[WARN] gamepads disabled, only the keyboard is read: {err}");
+                None
+            }
+        };
+        let gamepads = gilrs
+            .iter()
+            .flat_map(Gilrs::gamepads)
+            .map(|(id, gamepad)| {
+                let state = GamepadState {
+                    name: gamepad.name().to_string(),
+                    ..Default::default()
+                };
+                (id, state)
+            })
+            .collect();
+        Self {
+            gilrs,
+            gamepads,
+            events: Vec::new(),
+            toast: None,
+        }
+    }
+
+    /// Hub of a computer without gamepads, fed with hand-made events
+    #[cfg(test)]
+    pub(super) fn without_gilrs() -> Self {
+        Self {
+            gilrs: None,
+            gamepads: AHashMap::new(),
+            events: Vec::new(),
+            toast: None,
+        }
+    }
+
+    /// Reads the events since the last frame, to be called once at the start of each frame
+    pub fn poll(&mut self) {
+        let mut events = Vec::new();
+        while let Some(event) = self.gilrs.as_mut().and_then(Gilrs::next_event) {
+            events.push(event);
+        }
+        self.receive(events);
+    }
+
+    /// Applies the events of a frame, in the order they happened
+    pub(super) fn receive(&mut self, events: Vec<Event>) {
+        self.events.clear();
+        for event in events {
+            self.apply(event);
+            self.events.push(event);
+        }
+        if self
+            .toast
+            .as_ref()
+            .is_some_and(|(_, until)| Instant::now() > *until)
+        {
+            self.toast = None;
+        }
+    }
+
+    fn apply(&mut self, Event { id, event, .. }: Event) {
+        match event {
+            EventType::Connected => {
+                let name = self.gilrs.as_ref().map_or_else(
+                    || format!("Gamepad {id}"),
+                    |gilrs| gilrs.gamepad(id).name().to_string(),
+                );
+                self.show_toast(format!("{name} connected"));
+                self.gamepads.insert(
+                    id,
+                    GamepadState {
+                        name,
+                        ..Default::default()
+                    },
+                );
+            }
+            EventType::Disconnected => {
+                if let Some(gamepad) = self.gamepads.remove(&id) {
+                    self.show_toast(format!("{} disconnected", gamepad.name));
+                }
+            }
+            EventType::ButtonChanged(button, value, _) => self.set_button(id, button, value),
+            EventType::AxisChanged(axis, value, _) => {
+                if let Some(gamepad) = self.gamepads.get_mut(&id) {
+                    gamepad.axes.insert(axis, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the value of a button of a gamepad, ignored for a gamepad not connected
+    pub(super) fn set_button(&mut self, id: GamepadId, button: Button, value: f32) {
+        if let Some(gamepad) = self.gamepads.get_mut(&id) {
+            gamepad.buttons.insert(button, value);
+        }
+    }
+
+    /// Events of the last poll, in the order they happened
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Shows a message for a few seconds over the screens
+    pub fn show_toast(&mut self, message: String) {
+        self.toast = Some((message, Instant::now() + TOAST_DURATION));
+    }
+
+    /// Message about the last gamepad connected or disconnected, or given to `show_toast`
+    pub fn toast(&self) -> Option<&str> {
+        self.toast.as_ref().map(|(message, _)| message.as_str())
+    }
+
+    /// Gamepads connected during the last poll
+    pub fn connected(&self) -> impl Iterator<Item = GamepadId> + '_ {
+        self.events_of(EventType::Connected)
+    }
+
+    /// Gamepads disconnected during the last poll
+    pub fn disconnected(&self) -> impl Iterator<Item = GamepadId> + '_ {
+        self.events_of(EventType::Disconnected)
+    }
+
+    fn events_of(&self, event_type: EventType) -> impl Iterator<Item = GamepadId> + '_ {
+        self.events
+            .iter()
+            .filter(move |event| event.event == event_type)
+            .map(|event| event.id)
+    }
+
+    /// Connected gamepads, in the order they were first seen
+    pub fn gamepads(&self) -> impl Iterator<Item = (GamepadId, &GamepadState)> {
+        let mut gamepads: Vec<_> = self
+            .gamepads
+            .iter()
+            .map(|(id, gamepad)| (*id, gamepad))
+            .collect();
+        gamepads.sort_by_key(|(id, _)| usize::from(*id));
+        gamepads.into_iter()
+    }
+}
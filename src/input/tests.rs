@@ -0,0 +1,199 @@
+use gilrs::{Axis, Button, Event, EventType, GamepadId};
+use macroquad::input::KeyCode;
+
+use super::*;
+use crate::save::test_support::temp_dir;
+
+#[test]
+fn rebind_replaces_a_single_input() {
+    let mut bindings = Bindings::default();
+    bindings.rebind(Action::Accelerate, Binding::Key(KeyCode::I));
+    assert_eq!(
+        bindings.get(Action::Accelerate),
+        [
+            Binding::Key(KeyCode::I),
+            Binding::Key(KeyCode::Z),
+            Binding::Key(KeyCode::W),
+            Binding::Button(Button::RightTrigger2),
+        ]
+    );
+
+    // an input already bound to the action is not bound twice
+    bindings.rebind(Action::Accelerate, Binding::Key(KeyCode::W));
+    assert_eq!(
+        bindings.get(Action::Accelerate),
+        [
+            Binding::Key(KeyCode::W),
+            Binding::Key(KeyCode::Z),
+            Binding::Button(Button::RightTrigger2),
+        ]
+    );
+
+    let stick = Binding::Axis {
+        axis: Axis::RightStickY,
+        positive: true,
+    };
+    bindings.rebind(Action::Accelerate, stick);
+    assert_eq!(
+        bindings.get(Action::Accelerate),
+        [Binding::Key(KeyCode::W), Binding::Key(KeyCode::Z), stick,]
+    );
+    // the other actions keep their inputs
+    assert_eq!(
+        bindings.get(Action::Brake),
+        Bindings::default().get(Action::Brake)
+    );
+
+    // an action without inputs of the device gets one
+    bindings.actions.remove(&Action::Boost);
+    bindings.rebind(Action::Boost, Binding::Button(Button::South));
+    assert_eq!(
+        bindings.get(Action::Boost),
+        [Binding::Button(Button::South)]
+    );
+}
+
+#[test]
+fn bindings_are_read_back_after_saving() {
+    let dir = temp_dir("bindings");
+    let path = dir.join("nested").join("controls.ron");
+    assert_eq!(Bindings::load_from(&path), None);
+
+    let mut bindings = Bindings {
+        deadzone: 0.2,
+        ..Default::default()
+    };
+    bindings.rebind(Action::SteerLeft, Binding::Key(KeyCode::J));
+    bindings.rebind(
+        Action::SteerRight,
+        Binding::Axis {
+            axis: Axis::DPadX,
+            positive: true,
+        },
+    );
+    bindings.save_to(&path);
+    assert_eq!(Bindings::load_from(&path), Some(bindings));
+
+    // an invalid file is ignored for the shipped controls
+    std::fs::write(&path, "(deadzone:").unwrap();
+    assert_eq!(Bindings::load_from(&path), None);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn shipped_controls_are_valid() {
+    let shipped = Bindings::load_from(std::path::Path::new("assets/controls.ron"));
+    assert_eq!(shipped, Some(Bindings::default()));
+}
+
+fn pad(index: usize) -> GamepadId {
+    ron::from_str(&format!("({index})")).unwrap()
+}
+
+fn event(index: usize, event: EventType) -> Event {
+    Event::new(pad(index), event)
+}
+
+// the events of the buttons hold a code of the platform, their value is set directly
+fn trigger(hub: &mut InputHub, index: usize, value: f32) {
+    hub.set_button(pad(index), Button::RightTrigger2, value);
+}
+
+#[test]
+fn each_player_reads_its_own_gamepad() {
+    let mut hub = InputHub::without_gilrs();
+    hub.receive(vec![
+        event(0, EventType::Connected),
+        event(1, EventType::Connected),
+    ]);
+    trigger(&mut hub, 1, 1.0);
+    let bindings = Bindings::default();
+    let mut first = PlayerControls::new(&bindings, Some(Device::Gamepad(pad(0))));
+    let mut second = PlayerControls::new(&bindings, Some(Device::Gamepad(pad(1))));
+    assert_eq!(first.update(&hub).accelerate, 0.0);
+    assert_eq!(second.update(&hub).accelerate, 1.0);
+    // under the deadzone, the trigger is let go
+    trigger(&mut hub, 0, 0.05);
+    trigger(&mut hub, 1, 0.05);
+    assert_eq!(first.update(&hub).accelerate, 0.0);
+    assert_eq!(second.update(&hub).accelerate, 0.0);
+
+    trigger(&mut hub, 1, 0.8);
+    hub.receive(vec![event(1, EventType::Disconnected)]);
+    first.update(&hub);
+    assert!(!first.disconnected);
+    assert_eq!(second.update(&hub).accelerate, 0.0);
+    assert!(second.disconnected);
+    // the state of a gamepad plugged back in starts over
+    hub.receive(vec![event(1, EventType::Connected)]);
+    assert_eq!(second.update(&hub).accelerate, 0.0);
+    assert!(!second.disconnected);
+}
+
+#[test]
+fn connection_changes_are_shown_once() {
+    let mut hub = InputHub::without_gilrs();
+    assert_eq!(hub.toast(), None);
+    hub.receive(vec![event(3, EventType::Connected)]);
+    assert_eq!(hub.toast(), Some("Gamepad 3 connected"));
+    assert_eq!(hub.connected().collect::<Vec<_>>(), [pad(3)]);
+    assert_eq!(hub.gamepads().next().unwrap().1.name, "Gamepad 3");
+
+    // the events are those of the last poll, the message stays for a while
+    hub.receive(Vec::new());
+    assert_eq!(hub.connected().count(), 0);
+    assert_eq!(hub.toast(), Some("Gamepad 3 connected"));
+
+    hub.receive(vec![event(3, EventType::Disconnected)]);
+    assert_eq!(hub.toast(), Some("Gamepad 3 disconnected"));
+    assert_eq!(hub.disconnected().collect::<Vec<_>>(), [pad(3)]);
+    assert_eq!(hub.gamepads().count(), 0);
+    // a gamepad never seen is not told about
+    hub.show_toast("Saved".to_string());
+    hub.receive(vec![event(4, EventType::Disconnected)]);
+    trigger(&mut hub, 4, 1.0);
+    assert_eq!(hub.toast(), Some("Saved"));
+    assert_eq!(hub.gamepads().count(), 0);
+}
+
+#[test]
+fn players_get_the_keyboard_then_the_gamepads() {
+    let mut hub = InputHub::without_gilrs();
+    hub.receive(vec![
+        event(2, EventType::Connected),
+        event(0, EventType::Connected),
+    ]);
+    // a single player uses every device
+    assert_eq!(Device::assign(&hub, 1), [None]);
+    assert_eq!(
+        Device::assign(&hub, 2),
+        [Some(Device::Keyboard), Some(Device::Gamepad(pad(0)))]
+    );
+    // without enough devices, the last players are left out
+    assert_eq!(
+        Device::assign(&hub, 4),
+        [
+            Some(Device::Keyboard),
+            Some(Device::Gamepad(pad(0))),
+            Some(Device::Gamepad(pad(2))),
+        ]
+    );
+}
+
+#[test]
+fn a_gamepad_plugged_back_in_gets_its_seat_back() {
+    let devices = [
+        Some(Device::Keyboard),
+        Some(Device::Gamepad(pad(0))),
+        Some(Device::Gamepad(pad(1))),
+    ];
+    // both gamepads were unplugged, the second one comes back first
+    let waiting = [false, true, true];
+    assert_eq!(seat_for(pad(1), &devices, &waiting), Some(2));
+    assert_eq!(seat_for(pad(0), &devices, &waiting), Some(1));
+    // a new gamepad replaces the first one lost
+    assert_eq!(seat_for(pad(5), &devices, &waiting), Some(1));
+    assert_eq!(seat_for(pad(0), &devices, &[false, false, false]), None);
+    // a single player waits for any gamepad
+    assert_eq!(seat_for(pad(5), &[None], &[true]), Some(0));
+}
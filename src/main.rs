@@ -1,91 +1,717 @@
 use game::{
-    clear_viewport, play_music, set_background_cam, set_player_cam, update_viewport, Countdown,
-    GameMode, GameState, Level, Levels, MusicParams, Player, Timer,
+    car_texture, clear_viewport, crossfade, delta, draw_collisions, draw_respawn_fade, play_music,
+    set_background_cam, set_player_cam, split_viewport, update_viewport, Countdown, FixedTimestep,
+    GameMode, GameState, Ghost, Hud, Level, LevelCache, Levels, MusicParams, Player, Timer,
+    OPPONENT_TINTS,
 };
-use gilrs::*;
-use macroquad::audio::{load_sound, set_sound_volume, stop_sound};
+use macroquad::audio::{load_sound, set_sound_volume, stop_sound, Sound};
+use macroquad::experimental::coroutines::{stop_coroutine, Coroutine};
 use macroquad::prelude::*;
 
 use std::error::Error;
+use std::path::Path;
 
 mod config;
+mod editor;
 mod game;
 mod gui;
 mod input;
+mod net;
+mod save;
+mod sim;
 
-use config::Config;
+use config::{Config, RaceSettings};
+use gui::{draw_toast, GuiResources, PauseAction, PauseMenu, PlayerResult, RaceResults};
+use input::{seat_for, Bindings, Device, InputHub, MenuInput};
+use net::{Client, Host, Session, DEFAULT_PORT};
+use save::{
+    edited_levels_dir, load_edited_levels, load_replay, save_replay, GhostFrame, LevelRecord,
+    Records,
+};
+use sim::{
+    resolve_collisions, CarStat, Difficulty, RaceEvent, RaceState, Racer, RacerState, RacingLine,
+    Replay, Track, SIMULATION_RATE,
+};
 
+/// What a race borrows from the rest of the game
+struct RaceContext<'a> {
+    hub: &'a mut InputHub,
+    bindings: &'a mut Bindings,
+    resources: &'a GuiResources,
+    /// music of the level, toggled from the pause menu
+    music: &'a mut MusicParams,
+    settings: &'a mut RaceSettings,
+    /// race shared with the local network
+    session: Option<&'a mut Session>,
+}
+
+/// How the players left the race
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum RaceExit {
+    /// rank of each local player when it crossed the finish line
+    Finished {
+        positions: Vec<usize>,
+        racers: usize,
+    },
+    Restart,
+    Quit,
+}
+
+/// What a race keeps for each local player, besides its car
+#[derive(Default)]
+struct Seat {
+    // message shown for a few seconds after a lap
+    lap_message: Option<(String, Timer)>,
+    // current lap, recorded for the ghost
+    lap_frames: Vec<GhostFrame>,
+    // rank when the car crossed the finish line
+    position: usize,
+    // the gamepad of the player was disconnected and not plugged back in yet
+    waiting_pad: bool,
+}
+
+/// Cars driven by the computer, placed on the grid from `first_slot`
+fn spawn_opponents(
+    texture: &Texture2D,
+    stat: &CarStat,
+    track: &Track,
+    first_slot: usize,
+    count: usize,
+    difficulty: Difficulty,
+) -> Vec<Player> {
+    (first_slot..first_slot + count)
+        .map(|slot| {
+            let mut opponent = Player::opponent(texture, stat, track, slot, difficulty);
+            opponent.tint = OPPONENT_TINTS[(slot - first_slot) % OPPONENT_TINTS.len()];
+            opponent.sprite.set_animation(0);
+            opponent
+        })
+        .collect()
+}
+
+/// Rank of a racer among the cars of the race, from 1
+fn race_position(racer: &Racer, racers: &[&Racer], racing_line: &RacingLine) -> usize {
+    1 + racers
+        .iter()
+        .filter(|other| other.ahead_of(racer, racing_line))
+        .count()
+}
+
+/// Races the local players against the opponents, each player on its own part of the screen.
+/// The race is only recorded for a single player.
+/// Online, the host simulates every car and a client predicts its own one.
 async fn play_level(
-    player: &mut Player,
+    players: &mut [Player],
     level: &mut Level,
-    timer: &mut Timer,
-    gilrs: &mut Gilrs,
-    font: &Font,
-) {
-    player.init(level.starting_position);
+    context: RaceContext<'_>,
+    record: &mut LevelRecord,
+    playback: Option<&Replay>,
+) -> (RaceExit, Replay) {
+    let RaceContext {
+        hub,
+        bindings,
+        resources,
+        music,
+        settings,
+        mut session,
+    } = context;
+    let font = &resources.font;
     let mut countdown = Countdown::new(4.0);
+    let mut seats: Vec<Seat> = players
+        .iter()
+        .map(|_| Seat {
+            position: 1,
+            ..Seat::default()
+        })
+        .collect();
+    let mut fixed_step = FixedTimestep::new(SIMULATION_RATE);
+    let mut show_collisions = false;
+    let mut menu_input = MenuInput::new();
+    // the records and the ghost of the best lap are only kept for a player racing alone
+    let solo = players.len() == 1;
+    let mut ghost = Ghost::new(&players[0].texture);
+    let hud = Hud::new(&level.track, &level.racing_line);
+    // a replay is played against the opponents it was recorded with,
+    // the opponents of a client are simulated by the host
+    let (opponent_count, difficulty) = match playback {
+        Some(replay) => (replay.opponents, replay.difficulty),
+        None if matches!(session, Some(Session::Client(_))) => (0, settings.difficulty),
+        None if !level.racing_line.is_empty() => (settings.opponents, settings.difficulty),
+        None => (0, settings.difficulty),
+    };
+    // every input given to the simulation, and the inputs played back
+    let mut recording = Replay::new(
+        env!("CARGO_PKG_VERSION"),
+        &level.name,
+        players[0].racer.car.stat,
+        opponent_count,
+        difficulty,
+    );
+    let mut playback = playback.map(|replay| (replay, replay.inputs().peekable()));
+    // whether the replay ended in the recorded state
+    let mut verified: Option<bool> = None;
+    // the race is frozen while the menu is open, unless it is shared
+    let mut pause_menu: Option<PauseMenu> = None;
+    let texture = players[0].texture.clone();
+    let stat = players[0].racer.car.stat;
+    // the opponents start behind the players
+    let mut opponents = spawn_opponents(
+        &texture,
+        &stat,
+        &level.track,
+        players.len(),
+        opponent_count,
+        difficulty,
+    );
+    // cars of the players on the other computers, driven by their inputs on the host.
+    // On a client, every other car follows the states sent by the host.
+    let mut remotes: Vec<Player> = Vec::new();
+    // slots of the cars in the order they crossed the finish line
+    let mut finish_order: Vec<usize> = Vec::new();
+    // the host waits for the clients to join, a client for the host to start
+    let mut lobby = session.is_some();
+    // race and slot of the client, both given by the host
+    let mut client_race = None;
+    let mut client_slot = 0;
+    match session.as_deref_mut() {
+        Some(Session::Host(host)) => host.open_lobby(),
+        Some(Session::Client(client)) => {
+            if let Some(info) = &client.lobby {
+                client_race = Some(info.race);
+                client_slot = info.slot;
+            }
+        }
+        None => {}
+    }
 
-    loop {
-        countdown.update(get_frame_time() as f64);
+    let exit = loop {
+        let paused = pause_menu.is_some();
+        let frozen = paused && session.is_none();
+        if !frozen && !lobby {
+            countdown.update(get_frame_time() as f64);
+        }
 
         clear_background(BLACK);
 
-        let viewport = update_viewport();
+        let screen = update_viewport();
+        let viewports = split_viewport(screen, players.len());
+
+        hub.poll();
+        for player in players.iter_mut() {
+            player.handle_input(hub);
+        }
+        menu_input.update(hub, bindings);
+
+        for (player, seat) in players.iter().zip(&mut seats) {
+            // a single player still has the other gamepads
+            if player.gamepad_disconnected()
+                && (player.device().is_some() || hub.gamepads().next().is_none())
+            {
+                seat.waiting_pad = true;
+            }
+        }
+        // a gamepad plugged in is given to the first player who lost theirs
+        for id in hub.connected() {
+            let devices: Vec<Option<Device>> = players.iter().map(Player::device).collect();
+            let waiting: Vec<bool> = seats.iter().map(|seat| seat.waiting_pad).collect();
+            let Some(index) = seat_for(id, &devices, &waiting) else {
+                continue;
+            };
+            if players[index].device().is_some() {
+                players[index].set_device(Some(Device::Gamepad(id)));
+            }
+            seats[index].waiting_pad = false;
+        }
+
+        match session.as_deref_mut() {
+            Some(Session::Host(host)) => {
+                host.receive();
+                if lobby {
+                    // the opponents make room for the clients who joined
+                    if host.clients() != remotes.len() {
+                        remotes = (0..host.clients())
+                            .map(|client| {
+                                Player::remote(&texture, &stat, &level.track, Host::slot(client))
+                            })
+                            .collect();
+                        opponents = spawn_opponents(
+                            &texture,
+                            &stat,
+                            &level.track,
+                            players.len() + remotes.len(),
+                            opponent_count,
+                            difficulty,
+                        );
+                    }
+                    let racers = players.len() + remotes.len() + opponents.len();
+                    host.send_lobby(&level.name, stat, racers);
+                    if menu_input.click && !paused {
+                        host.start();
+                        lobby = false;
+                    }
+                }
+            }
+            Some(Session::Client(client)) => {
+                client.receive();
+                if client.disconnected() {
+                    hub.show_toast("Lost the connection to the host".to_string());
+                    break RaceExit::Quit;
+                }
+                let Some(info) = client
+                    .lobby
+                    .as_ref()
+                    .filter(|info| Some(info.race) == client_race)
+                else {
+                    // the host opened its next race
+                    break RaceExit::Restart;
+                };
+                // the slots change while clients join and leave
+                if lobby && info.slot != client_slot {
+                    client_slot = info.slot;
+                    players[0].racer = Racer::on_grid(stat, &level.track, client_slot);
+                    players[0].interpolate(1.0);
+                }
+                // the other cars wait on the grid until the first snapshot
+                if lobby && remotes.len() + 1 != info.racers {
+                    remotes = (0..info.racers)
+                        .filter(|slot| *slot != client_slot)
+                        .map(|slot| Player::remote(&texture, &stat, &level.track, slot))
+                        .collect();
+                }
+                if let Some(snapshot) = client.take_snapshot() {
+                    lobby = false;
+                    countdown.sync(snapshot.countdown);
+                    if remotes.len() + 1 != snapshot.racers.len() {
+                        remotes = (0..snapshot.racers.len())
+                            .filter(|slot| *slot != client_slot)
+                            .map(|slot| Player::remote(&texture, &stat, &level.track, slot))
+                            .collect();
+                    }
+                    let others = snapshot
+                        .racers
+                        .iter()
+                        .enumerate()
+                        .filter(|(slot, _)| *slot != client_slot);
+                    for (remote, (_, state)) in remotes.iter_mut().zip(others) {
+                        remote.follow(state);
+                    }
+                    if let (Some(acked), Some(state)) =
+                        (snapshot.acked, snapshot.racers.get(client_slot))
+                    {
+                        client.prediction.reconcile(
+                            &mut players[0].racer,
+                            state,
+                            acked,
+                            &level.track,
+                            fixed_step.step(),
+                        );
+                    }
+                    finish_order = snapshot.finish_order;
+                }
+            }
+            None => {}
+        }
+
+        if !frozen && !lobby && countdown.finished() {
+            for player in players.iter_mut() {
+                if !player.racer.race.finished() {
+                    player.animate(get_frame_time());
+                }
+            }
+            for other in remotes.iter_mut().chain(&mut opponents) {
+                other.animate(get_frame_time());
+            }
 
-        if countdown.finished() {
-            player.update(gilrs, level);
-            player.sprite.update();
-            timer.update(get_frame_time() as f64);
+            for _ in 0..fixed_step.update(get_frame_time() as f64) {
+                // the opponents keep racing once the players have finished
+                for opponent in &mut opponents {
+                    opponent.drive(&level.racing_line, fixed_step.step());
+                    opponent.update(&level.track, fixed_step.step());
+                }
+                if let Some(Session::Host(host)) = session.as_deref_mut() {
+                    for (client, remote) in remotes.iter_mut().enumerate() {
+                        remote.set_input(host.next_input(client));
+                        remote.update(&level.track, fixed_step.step());
+                    }
+                }
+
+                if !players.iter().all(|player| player.racer.race.finished()) {
+                    if let Some((replay, inputs)) = &mut playback {
+                        let Some(input) = inputs.next() else {
+                            verified.get_or_insert(players[0].racer.state_hash() == replay.hash);
+                            break;
+                        };
+                        players[0].set_input(input);
+                    }
+                    recording.push(players[0].input());
+                }
+
+                let mut finished = Vec::new();
+                for (index, (player, seat)) in players.iter_mut().zip(&mut seats).enumerate() {
+                    if player.racer.race.finished() {
+                        continue;
+                    }
+                    let event = match session.as_deref_mut() {
+                        Some(Session::Client(client)) => {
+                            player.predict(&mut client.prediction, &level.track, fixed_step.step())
+                        }
+                        _ => player.update(&level.track, fixed_step.step()),
+                    };
+                    let race = &player.racer.race;
+                    let lap_message = &mut seat.lap_message;
+                    let lap_frames = &mut seat.lap_frames;
+                    match event {
+                        Some(RaceEvent::Checkpoint { index, split }) => {
+                            let best = record.best_splits.get(index).copied().filter(|_| solo);
+                            *lap_message = Some((
+                                format!(
+                                    "Checkpoint {}: {split:.2}s{}",
+                                    index + 1,
+                                    delta(split, best)
+                                ),
+                                Timer::new(2.0),
+                            ));
+                        }
+                        Some(RaceEvent::MissedCheckpoint) => {
+                            *lap_message = Some(("Missed checkpoint".to_string(), Timer::new(2.0)));
+                        }
+                        Some(RaceEvent::LapCompleted { lap, time }) => {
+                            let best = record.best_lap.filter(|_| solo);
+                            let splits = race.lap_splits.last().map_or(&[][..], Vec::as_slice);
+                            let new_record = if solo && record.record_lap(time, splits, lap_frames)
+                            {
+                                "  New record!"
+                            } else {
+                                ""
+                            };
+                            *lap_message = Some((
+                                format!("Lap {lap}: {time:.2}s{}{new_record}", delta(time, best)),
+                                Timer::new(2.0),
+                            ));
+                        }
+                        Some(RaceEvent::Finished { total }) => {
+                            finished.push(index);
+                            // the last lap is only completed with the race
+                            if let (true, Some(time), Some(splits)) =
+                                (solo, race.lap_times.last(), race.lap_splits.last())
+                            {
+                                record.record_lap(*time, splits, lap_frames);
+                            }
+                            let best = record.best_time.filter(|_| solo);
+                            let new_record = if solo && record.record_time(total) {
+                                "  New record!"
+                            } else {
+                                ""
+                            };
+                            let respawns = match race.respawns {
+                                0 => String::new(),
+                                1 => " (1 respawn)".to_string(),
+                                n => format!(" ({n} respawns)"),
+                            };
+                            *lap_message = Some((
+                                format!(
+                                    "Finished: {total:.2}s{}{respawns}{new_record}",
+                                    delta(total, best)
+                                ),
+                                Timer::new(f64::MAX),
+                            ));
+                        }
+                        Some(RaceEvent::LapStarted(_)) | None => {}
+                    }
+
+                    if matches!(
+                        event,
+                        Some(RaceEvent::LapStarted(_) | RaceEvent::LapCompleted { .. })
+                    ) {
+                        lap_frames.clear();
+                    }
+                    lap_frames.push(player.ghost_frame());
+                }
+
+                // the players come first, as when a replay is verified.
+                // The host resolves the collisions of its clients.
+                if !matches!(session, Some(Session::Client(_))) {
+                    let mut racers: Vec<&mut Racer> = players
+                        .iter_mut()
+                        .chain(&mut remotes)
+                        .chain(&mut opponents)
+                        .map(|player| &mut player.racer)
+                        .collect();
+                    resolve_collisions(&mut racers);
+                }
+
+                let racers: Vec<&Racer> = players
+                    .iter()
+                    .chain(&remotes)
+                    .chain(&opponents)
+                    .map(|player| &player.racer)
+                    .collect();
+                if !matches!(session, Some(Session::Client(_))) {
+                    for (slot, racer) in racers.iter().enumerate() {
+                        if racer.race.finished() && !finish_order.contains(&slot) {
+                            finish_order.push(slot);
+                        }
+                    }
+                }
+                for index in finished {
+                    seats[index].position =
+                        race_position(&players[index].racer, &racers, &level.racing_line);
+                }
+
+                if players[0].racer.race.finished() {
+                    if let Some((replay, _)) = &playback {
+                        verified.get_or_insert(players[0].racer.state_hash() == replay.hash);
+                    }
+                }
+            }
+        }
+        for player in players.iter_mut().chain(&mut remotes).chain(&mut opponents) {
+            player.interpolate(fixed_step.alpha());
         }
 
-        // draw background
-        set_background_cam(player, viewport);
-        level.draw_background();
-        clear_viewport();
+        match session.as_deref_mut() {
+            Some(Session::Host(host)) if !lobby => {
+                let states: Vec<RacerState> = players
+                    .iter()
+                    .chain(&remotes)
+                    .chain(&opponents)
+                    .map(|player| player.racer.state())
+                    .collect();
+                host.send_state(countdown.time_left(), &states, &finish_order);
+            }
+            Some(Session::Client(client)) => client.send_inputs(),
+            _ => {}
+        }
 
-        // main cam
-        set_player_cam(player, viewport);
-        level.draw_circuit();
-        player.draw();
-        clear_viewport();
+        for (index, viewport) in viewports.iter().enumerate() {
+            // draw background
+            set_background_cam(&players[index], *viewport);
+            level.draw_background();
+            clear_viewport();
+
+            // main cam
+            set_player_cam(&players[index], *viewport);
+            level.draw_circuit();
+            let race = &players[0].racer.race;
+            if solo && race.state == RaceState::Racing {
+                let lap_time = race.lap_time(players[0].racer.time)
+                    + fixed_step.alpha() as f64 * fixed_step.step();
+                ghost.draw(&record.ghost, lap_time);
+            }
+            for other in remotes.iter_mut().chain(&mut opponents) {
+                other.draw();
+            }
+            for player in players.iter_mut() {
+                player.draw();
+            }
+            if show_collisions {
+                draw_collisions(&players[index].racer.car, &level.track);
+            }
+            clear_viewport();
+        }
 
         // draw ui
         set_default_camera();
+        let racers: Vec<&Racer> = players
+            .iter()
+            .chain(&remotes)
+            .chain(&opponents)
+            .map(|player| &player.racer)
+            .collect();
+        for (index, (player, seat)) in players.iter().zip(&mut seats).enumerate() {
+            let viewport = viewports[index];
+            if let Some(elapsed) = player.racer.respawn {
+                draw_respawn_fade(elapsed, viewport);
+            }
 
-        countdown.draw(font);
+            let others: Vec<Vec2> = racers
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, racer)| racer.car.position)
+                .collect();
+            let position = race_position(&player.racer, &racers, &level.racing_line);
+            hud.draw(
+                &player.racer,
+                record.best_lap.filter(|_| solo),
+                position,
+                &others,
+                viewport,
+                font,
+            );
 
-        draw_text_ex(
-            format!("{:.2}s", timer.elapsed()).as_str(),
-            20.0,
-            30.0,
-            TextParams {
-                font: Some(font),
-                font_size: 20,
-                color: WHITE,
-                ..Default::default()
-            },
-        );
+            if let Some((message, message_timer)) = &mut seat.lap_message {
+                let (x, y, w, h) = viewport;
+                let center = get_text_center(message, Some(font), 40, 1.0, 0.0);
+                draw_text_ex(
+                    message,
+                    x as f32 + w as f32 * 0.5 - center.x,
+                    y as f32 + h as f32 * 0.3 - center.y,
+                    TextParams {
+                        font: Some(font),
+                        font_size: 40,
+                        color: WHITE,
+                        ..Default::default()
+                    },
+                );
+                if !frozen && message_timer.update(get_frame_time() as f64) {
+                    seat.lap_message = None;
+                }
+            }
+        }
+
+        if lobby {
+            let status = match session.as_deref() {
+                Some(Session::Host(host)) => {
+                    let addr = host
+                        .local_addr()
+                        .map_or_else(|_| String::new(), |addr| format!(" on {addr}"));
+                    format!("Hosting{addr}, {} joined. Confirm to start", host.clients())
+                }
+                _ => "Waiting for the host to start".to_string(),
+            };
+            let center = get_text_center(&status, Some(font), 30, 1.0, 0.0);
+            draw_text_ex(
+                &status,
+                screen_width() * 0.5 - center.x,
+                screen_height() * 0.5 - center.y,
+                TextParams {
+                    font: Some(font),
+                    font_size: 30,
+                    color: WHITE,
+                    ..Default::default()
+                },
+            );
+        } else {
+            countdown.draw(font);
+        }
 
         draw_text(
             format!("FPS: {}", get_fps()).as_str(),
-            10.0,
-            40.0,
+            screen_width() * 0.5 - 40.0,
+            20.0,
             20.0,
             WHITE,
         );
 
-        if is_key_pressed(KeyCode::Escape) {
-            break;
+        if playback.is_some() {
+            let status = match verified {
+                None => "Replay",
+                Some(true) => "Replay verified",
+                Some(false) => "Replay diverged",
+            };
+            let center = get_text_center(status, Some(font), 20, 1.0, 0.0);
+            draw_text_ex(
+                status,
+                screen_width() * 0.5 - center.x,
+                50.0,
+                TextParams {
+                    font: Some(font),
+                    font_size: 20,
+                    color: WHITE,
+                    ..Default::default()
+                },
+            );
         }
 
+        if is_key_pressed(KeyCode::F1) {
+            show_collisions ^= true;
+        }
+
+        let pause_pressed = players.iter().any(Player::pause_pressed);
+        // the host waits for the clients still racing
+        let all_finished = players.iter().all(|player| player.racer.race.finished())
+            && match session.as_deref() {
+                Some(Session::Host(host)) => remotes
+                    .iter()
+                    .enumerate()
+                    .all(|(client, remote)| remote.racer.race.finished() || host.left(client)),
+                _ => true,
+            };
+        if let Some(menu) = &mut pause_menu {
+            let waiting: Vec<String> = seats
+                .iter()
+                .enumerate()
+                .filter(|(_, seat)| seat.waiting_pad)
+                .map(|(index, _)| (index + 1).to_string())
+                .collect();
+            menu.prompt = match waiting.len() {
+                0 => None,
+                _ if players.len() == 1 => {
+                    Some("Reconnect the controller, or resume with the keyboard".to_string())
+                }
+                1 => Some(format!("Reconnect the controller of player {}", waiting[0])),
+                _ => Some(format!(
+                    "Reconnect the controllers of players {}",
+                    waiting.join(", ")
+                )),
+            };
+            let action = if pause_pressed {
+                Some(PauseAction::Resume)
+            } else {
+                menu.update(resources, menu_input)
+            };
+            match action {
+                Some(PauseAction::Resume) => pause_menu = None,
+                Some(PauseAction::Restart) => break RaceExit::Restart,
+                Some(PauseAction::Options) => {
+                    let mut state = GameState::Options;
+                    loop {
+                        state = match state {
+                            GameState::Options => {
+                                gui::options(resources, hub, bindings, music, settings).await
+                            }
+                            GameState::Controls => gui::controls(resources, hub, bindings).await,
+                            _ => break,
+                        };
+                        play_music(music);
+                    }
+                    for player in players.iter_mut() {
+                        player.set_bindings(bindings);
+                    }
+                    menu_input = MenuInput::new();
+                }
+                Some(PauseAction::Quit) => break RaceExit::Quit,
+                None => {}
+            }
+        } else if pause_pressed || players.iter().any(Player::gamepad_disconnected) {
+            pause_menu = Some(PauseMenu::new());
+        } else if (all_finished || verified.is_some()) && menu_input.click {
+            // online, the order is the one the host saw
+            let slot = |index: usize| {
+                if session.is_some() {
+                    client_slot
+                } else {
+                    index
+                }
+            };
+            break RaceExit::Finished {
+                positions: seats
+                    .iter()
+                    .enumerate()
+                    .map(|(index, seat)| {
+                        finish_order
+                            .iter()
+                            .position(|finished| *finished == slot(index))
+                            .map_or(seat.position, |position| position + 1)
+                    })
+                    .collect(),
+                racers: players.len() + remotes.len() + opponents.len(),
+            };
+        }
+        draw_toast(resources, hub);
+
         next_frame().await;
-    }
+    };
+
+    recording.finish(&players[0].racer);
+    (exit, recording)
 }
 
 #[macroquad::main("BigRace")]
 async fn main() -> Result<(), Box<dyn Error>> {
     // detect controller
-    let mut gilrs = Gilrs::new().unwrap();
+    let mut hub = InputHub::new();
 
     //set_pc_assets_folder("assets");
 
@@ -94,7 +720,10 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     // load config file
     let config_str = std::fs::read_to_string("assets/config.ron").expect("read config.ron file");
-    let config: Config = ron::from_str(&config_str).expect("valid config.ron");
+    let mut config: Config = ron::from_str(&config_str).expect("valid config.ron");
+
+    // keyboard and gamepad bindings
+    let mut bindings = Bindings::load();
 
     // set the game state to begin at menu
     let mut game_state = GameState::Menu;
@@ -110,56 +739,355 @@ async fn main() -> Result<(), Box<dyn Error>> {
         volume: 1.0,
     };
 
+    // the assets of a level are only loaded when it is played
+    let levels_str = std::fs::read_to_string("assets/levels/levels.ron").expect("levels.ron file");
+    let mut levels: Levels = ron::from_str(&levels_str).expect("valid config level");
+    // kept to revert the edited levels
+    let shipped_levels = levels.levels.clone();
+    load_edited_levels(&edited_levels_dir(), &mut levels.levels);
+    let mut level_cache = LevelCache::new(levels.levels.len());
+
+    // level music fading out after a race
+    let mut music_fade: Option<(Coroutine, Sound)> = None;
+
+    // personal records of the player
+    let mut records = Records::load();
+
+    // race given on the command line with `--replay <file>`,
+    // only checked without being shown with `--verify`.
+    // `--racing-line <level>` prints the generated racing line of a level.
+    // `--host <address>` shares the races on the local network, `--join <address>` joins them.
+    let mut replay: Option<Replay> = None;
+    let mut session: Option<Session> = None;
+    // shown by the results screen
+    let mut results: Option<RaceResults> = None;
+    let mut replay_path: Option<String> = None;
+    let mut verify_only = false;
+    let mut racing_line_level: Option<String> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => replay_path = args.next(),
+            "--verify" => verify_only = true,
+            "--racing-line" => racing_line_level = args.next(),
+            "--host" | "--join" => {
+                let Some(addr) = args.next() else {
+                    eprintln!("[ERR] {arg} needs an address");
+                    continue;
+                };
+                // the port may be left out
+                let addr = if addr.contains(':') {
+                    addr
+                } else {
+                    format!("{addr}:{DEFAULT_PORT}")
+                };
+                let connected = if arg == "--host" {
+                    Host::bind(&addr).map(|host| {
+                        game_state = GameState::SelectLevel;
+                        Session::Host(host)
+                    })
+                } else {
+                    Client::connect(&addr).map(|client| {
+                        game_state = GameState::Lobby;
+                        Session::Client(client)
+                    })
+                };
+                match connected {
+                    Ok(connected) => session = Some(connected),
+                    Err(err) => eprintln!("[ERR] could not use {addr}: {err}"),
+                }
+            }
+            _ => eprintln!("[WARN] unknown argument {arg}"),
+        }
+    }
+    if let Some(name) = racing_line_level {
+        match levels.levels.iter().find(|conf| conf.name() == name) {
+            Some(conf) => match conf.generated_fields() {
+                Ok(fields) => println!("{fields}"),
+                Err(err) => eprintln!("[ERR] no racing line for {name}: {err}"),
+            },
+            None => eprintln!("[ERR] unknown level {name}"),
+        }
+        return Ok(());
+    }
+    if let Some(path) = replay_path {
+        match load_replay(Path::new(&path)) {
+            Ok(loaded) => {
+                if loaded.version != env!("CARGO_PKG_VERSION") {
+                    eprintln!(
+                        "[WARN] replay recorded with version {}, it may not play the same",
+                        loaded.version
+                    );
+                }
+                match levels
+                    .levels
+                    .iter()
+                    .position(|conf| conf.name() == loaded.level)
+                {
+                    Some(index) if verify_only => {
+                        let conf = &levels.levels[index];
+                        let track = conf.track();
+                        let verified = loaded.verify(&track, &conf.racing_line(&track));
+                        println!("{path}: {}", if verified { "verified" } else { "diverged" });
+                        return Ok(());
+                    }
+                    Some(index) => {
+                        game_state = GameState::Playing(GameMode::Replay, index);
+                        replay = Some(loaded);
+                    }
+                    None => eprintln!("[ERR] unknown level {} in {path}", loaded.level),
+                }
+            }
+            Err(err) => eprintln!("[ERR] invalid replay {path}: {err}"),
+        }
+    }
+
     loop {
+        // going back to the menu leaves the network
+        if matches!(game_state, GameState::Menu) {
+            if let Some(mut session) = session.take() {
+                session.close();
+            }
+        }
         match game_state {
             GameState::Menu => {
                 set_sound_volume(&main_theme.sound, main_theme.volume);
                 play_music(&mut main_theme);
-                game_state = gui::main_menu(&gui_resources, &mut gilrs).await;
+                game_state = gui::main_menu(&gui_resources, &mut hub, &bindings).await;
+            }
+            GameState::SelectLevel => {
+                play_music(&mut main_theme);
+                game_state = gui::select_level(
+                    &gui_resources,
+                    &mut hub,
+                    &bindings,
+                    &levels.levels,
+                    &records,
+                    session.as_mut(),
+                )
+                .await;
+                if let (Some(_), GameState::Playing(_, index)) = (&session, &game_state) {
+                    game_state = GameState::Playing(GameMode::Online, *index);
+                }
+            }
+            GameState::Lobby => {
+                game_state = match &mut session {
+                    Some(Session::Client(client)) => {
+                        play_music(&mut main_theme);
+                        gui::lobby(&gui_resources, &mut hub, &bindings, client, &levels.levels)
+                            .await
+                    }
+                    _ => GameState::Menu,
+                };
             }
-            GameState::Playing(GameMode::Arcade) => {
+            GameState::Playing(mode, level_index) => {
                 stop_sound(&main_theme.sound);
                 main_theme.is_playing = false;
-                // load resources
-                println!("[...] loading");
 
-                let mut player = Player::new(&config.carstat).await;
-                player.sprite.set_animation(0);
+                let level = level_cache
+                    .get(
+                        level_index,
+                        &levels.levels[level_index],
+                        &gui_resources.font,
+                    )
+                    .await;
 
-                let levels_str =
-                    std::fs::read_to_string("assets/levels/levels.ron").expect("levels.ron file");
-                let levels_config: Levels = ron::from_str(&levels_str).expect("valid config level");
+                // a replay is played with the car it was recorded with,
+                // a client races with the car of the host
+                let playback = replay.clone().filter(|_| mode == GameMode::Replay);
+                let lobby = match &session {
+                    Some(Session::Client(client)) => client.lobby.clone(),
+                    _ => None,
+                };
+                let stat = match (&playback, &lobby) {
+                    (Some(replay), _) => replay.stat,
+                    (_, Some(lobby)) => lobby.stat,
+                    _ => config.carstat,
+                };
+                // a replay only has the inputs of one player, online races have one per computer
+                let players = if playback.is_some() || session.is_some() {
+                    1
+                } else {
+                    config.race.players
+                };
+                let first_slot = lobby.as_ref().map_or(0, |lobby| lobby.slot);
+                let texture = car_texture().await;
+                let mut players: Vec<Player> = Device::assign(&hub, players)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, device)| {
+                        let slot = first_slot + index;
+                        let mut player =
+                            Player::new(&texture, &stat, &bindings, device, &level.track, slot);
+                        player.sprite.set_animation(0);
+                        player
+                    })
+                    .collect();
 
-                let mut current_level_index: usize = 1;
-
-                let mut levels = Vec::<Level>::new();
-
-                for level in levels_config.levels.clone().into_iter() {
-                    let loaded = Level::load(&level).await;
-                    levels.push(loaded);
+                // a fade still running would stop the level music
+                if let Some((fade, sound)) = music_fade.take() {
+                    stop_coroutine(fade);
+                    stop_sound(&sound);
                 }
+                let mut music = MusicParams {
+                    sound: level.music.clone(),
+                    is_playing: false,
+                    is_activated: main_theme.is_activated,
+                    volume: main_theme.volume,
+                };
+                // the level music starts with the countdown
+                play_music(&mut music);
 
-                println!("[OK] loaded.");
+                let name = levels.levels[level_index].name();
+                // kept to compare the race with the records it may beat
+                let previous = records.level(name).cloned().unwrap_or_default();
+                let context = RaceContext {
+                    hub: &mut hub,
+                    bindings: &mut bindings,
+                    resources: &gui_resources,
+                    music: &mut music,
+                    settings: &mut config.race,
+                    session: session.as_mut(),
+                };
+                let exit = if let Some(playback) = &playback {
+                    // records are not beaten by a replay
+                    let mut record = previous.clone();
+                    play_level(&mut players, level, context, &mut record, Some(playback))
+                        .await
+                        .0
+                } else {
+                    let (exit, recording) =
+                        play_level(&mut players, level, context, records.level_mut(name), None)
+                            .await;
+                    records.save();
+                    // only finished races are kept,
+                    // and the other cars of an online race are not in the replay
+                    let finished = matches!(exit, RaceExit::Finished { .. });
+                    if finished && recording.steps() > 0 && players.len() == 1 && session.is_none()
+                    {
+                        save_replay(&recording);
+                    }
+                    exit
+                };
+                // the music may have been turned off from the pause menu
+                main_theme.is_activated = music.is_activated;
 
-                let mut timer = Timer::new(6.0);
-
-                play_level(
-                    &mut player,
-                    &mut levels[current_level_index],
-                    &mut timer,
-                    &mut gilrs,
-                    &gui_resources.font,
+                game_state = match exit {
+                    // a client waits for the next race of the host
+                    RaceExit::Restart if matches!(session, Some(Session::Client(_))) => {
+                        stop_sound(&music.sound);
+                        GameState::Lobby
+                    }
+                    RaceExit::Restart => {
+                        stop_sound(&music.sound);
+                        GameState::Playing(mode, level_index)
+                    }
+                    RaceExit::Finished {
+                        ref positions,
+                        racers,
+                    } => {
+                        let mut race_results = RaceResults::new(
+                            mode,
+                            level_index,
+                            name,
+                            &players[0].racer,
+                            (positions[0], racers),
+                            previous,
+                            // the same players as the ones `play_level` keeps the records of
+                            playback.is_none() && players.len() == 1,
+                        );
+                        if players.len() > 1 {
+                            race_results.players = positions
+                                .iter()
+                                .zip(&players)
+                                .enumerate()
+                                .map(|(index, (position, player))| PlayerResult {
+                                    player: index,
+                                    position: *position,
+                                    time: player.racer.time,
+                                    best_lap: player
+                                        .racer
+                                        .race
+                                        .lap_times
+                                        .iter()
+                                        .copied()
+                                        .reduce(f64::min),
+                                })
+                                .collect();
+                            race_results.players.sort_by_key(|player| player.position);
+                        }
+                        results = Some(race_results);
+                        GameState::GameOver
+                    }
+                    RaceExit::Quit => GameState::Menu,
+                };
+                if exit != RaceExit::Restart {
+                    music_fade =
+                        Some((crossfade(&mut music, &mut main_theme), music.sound.clone()));
+                }
+            }
+            // without a race, the fallback below is shown
+            GameState::GameOver if results.is_some() => {
+                let results = results.as_ref().expect("results of the last race");
+                game_state = gui::results(
+                    &gui_resources,
+                    &mut hub,
+                    &bindings,
+                    results,
+                    levels.levels.len(),
+                    session.as_mut(),
                 )
                 .await;
-
-                game_state = GameState::Menu;
+                // the host chooses the next race of its clients
+                if let (Some(Session::Client(_)), GameState::Playing(..)) = (&session, &game_state)
+                {
+                    game_state = GameState::Lobby;
+                }
+            }
+            GameState::Editor(None) => {
+                game_state = match gui::select_level(
+                    &gui_resources,
+                    &mut hub,
+                    &bindings,
+                    &levels.levels,
+                    &records,
+                    None,
+                )
+                .await
+                {
+                    GameState::Playing(_, index) => GameState::Editor(Some(index)),
+                    state => state,
+                };
+            }
+            GameState::Editor(Some(index)) => {
+                play_music(&mut main_theme);
+                game_state = editor::editor(
+                    &gui_resources,
+                    &mut hub,
+                    &mut levels.levels[index],
+                    &shipped_levels[index],
+                    &mut records,
+                )
+                .await;
+                // the assets are loaded again with the tiles saved in the editor
+                level_cache.forget(index);
             }
             GameState::Credits => {
-                game_state = gui::credits(&gui_resources, &mut gilrs).await;
+                game_state = gui::credits(&gui_resources, &mut hub, &bindings).await;
             }
             GameState::Options => {
                 play_music(&mut main_theme);
-                game_state = gui::options(&gui_resources, &mut gilrs, &mut main_theme).await;
+                game_state = gui::options(
+                    &gui_resources,
+                    &mut hub,
+                    &bindings,
+                    &mut main_theme,
+                    &mut config.race,
+                )
+                .await;
+            }
+            GameState::Controls => {
+                game_state = gui::controls(&gui_resources, &mut hub, &mut bindings).await;
             }
             GameState::Quit => {
                 break Ok(());
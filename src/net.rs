@@ -0,0 +1,41 @@
+mod client;
+mod host;
+mod protocol;
+
+pub use client::*;
+pub use host::*;
+pub use protocol::*;
+
+use std::time::Duration;
+
+/// Port a host listens on when none is given
+pub const DEFAULT_PORT: u16 = 7777;
+/// Silence after which the other side is considered gone
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Race shared with other computers of the local network
+pub enum Session {
+    Host(Host),
+    Client(Client),
+}
+
+impl Session {
+    /// Keeps the other side waiting while this computer is in its menus between two races
+    pub fn keep_alive(&mut self) {
+        match self {
+            Session::Host(host) => host.keep_alive(),
+            Session::Client(client) => client.keep_alive(),
+        }
+    }
+
+    /// Tells the other side that this computer leaves
+    pub fn close(&mut self) {
+        match self {
+            Session::Host(host) => host.close(),
+            Session::Client(client) => client.leave(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
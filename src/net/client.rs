@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use super::{decode, encode, ClientMessage, HostMessage, Snapshot, MAX_DATAGRAM, TIMEOUT};
+use crate::sim::{CarStat, PlayerInput, RaceEvent, Racer, RacerState, Track};
+
+// most inputs sent again in one datagram while they are not acknowledged
+const MAX_RESENT_INPUTS: usize = 64;
+
+/// Inputs applied to the car of the player before the host saw them,
+/// to apply them again on the state the host sends back
+#[derive(Clone, Debug, Default)]
+pub struct Prediction {
+    // number of the next input
+    next: u32,
+    // each input not acknowledged yet with its number, and the racer before it was applied
+    pending: VecDeque<(u32, PlayerInput, Racer)>,
+}
+
+impl Prediction {
+    /// Applies an input to the car right away, it is kept until the host acknowledges it
+    pub fn step(
+        &mut self,
+        racer: &mut Racer,
+        input: PlayerInput,
+        track: &Track,
+        delta_time: f64,
+    ) -> Option<RaceEvent> {
+        self.pending.push_back((self.next, input, racer.clone()));
+        self.next += 1;
+        racer.step(&input, track, delta_time)
+    }
+
+    /// Moves the car where the host simulated it after the input `acked`,
+    /// then applies again the inputs the host has not seen yet
+    pub fn reconcile(
+        &mut self,
+        racer: &mut Racer,
+        state: &RacerState,
+        acked: u32,
+        track: &Track,
+        delta_time: f64,
+    ) {
+        while self
+            .pending
+            .front()
+            .is_some_and(|(number, _, _)| *number <= acked)
+        {
+            self.pending.pop_front();
+        }
+        // the progress of the race is the one predicted before the first input left
+        let mut corrected = self
+            .pending
+            .front()
+            .map_or_else(|| racer.clone(), |(_, _, before)| before.clone());
+        corrected.set_state(state);
+        for (_, input, before) in &mut self.pending {
+            *before = corrected.clone();
+            corrected.step(input, track, delta_time);
+        }
+        *racer = corrected;
+    }
+
+    /// Inputs not acknowledged yet, with the number of the first one
+    pub fn unacked(&self) -> (u32, Vec<PlayerInput>) {
+        let skipped = self.pending.len().saturating_sub(MAX_RESENT_INPUTS);
+        let first = self.next - (self.pending.len() - skipped) as u32;
+        let inputs = self
+            .pending
+            .iter()
+            .skip(skipped)
+            .map(|(_, input, _)| *input)
+            .collect();
+        (first, inputs)
+    }
+}
+
+/// Next race announced by the host
+#[derive(Clone, Debug)]
+pub struct Lobby {
+    pub race: u32,
+    pub level: String,
+    pub slot: usize,
+    pub stat: CarStat,
+    pub racers: usize,
+}
+
+/// Drives a car of a race simulated by a host of the local network
+#[derive(Debug)]
+pub struct Client {
+    socket: UdpSocket,
+    last_heard: Instant,
+    pub lobby: Option<Lobby>,
+    // newest state of the race of the lobby
+    snapshot: Option<Snapshot>,
+    /// the host is running a race without this client
+    pub busy: bool,
+    /// the host said it left
+    pub closed: bool,
+    pub prediction: Prediction,
+}
+
+impl Client {
+    /// Asks the host for a car in its next race
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let host = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no host address"))?;
+        let local: SocketAddr = if host.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(host)?;
+        socket.set_nonblocking(true)?;
+        let client = Self {
+            socket,
+            last_heard: Instant::now(),
+            lobby: None,
+            snapshot: None,
+            busy: false,
+            closed: false,
+            prediction: Prediction::default(),
+        };
+        client.join();
+        Ok(client)
+    }
+
+    /// Asks again for a car, the host may have missed it or forgotten the client
+    pub fn join(&self) {
+        self.send(&ClientMessage::Join {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        });
+    }
+
+    /// Reads the messages of the host
+    pub fn receive(&mut self) {
+        let mut buffer = vec![0; MAX_DATAGRAM];
+        while let Ok(size) = self.socket.recv(&mut buffer) {
+            let Some(message) = decode(&buffer[..size]) else {
+                continue;
+            };
+            self.last_heard = Instant::now();
+            match message {
+                HostMessage::Lobby {
+                    race,
+                    level,
+                    slot,
+                    stat,
+                    racers,
+                } => {
+                    // a new race starts without the inputs of the last one
+                    if self.lobby.as_ref().is_none_or(|lobby| lobby.race != race) {
+                        self.prediction = Prediction::default();
+                        self.snapshot = None;
+                    }
+                    self.busy = false;
+                    self.lobby = Some(Lobby {
+                        race,
+                        level,
+                        slot,
+                        stat,
+                        racers,
+                    });
+                }
+                HostMessage::State(snapshot) => {
+                    if self
+                        .lobby
+                        .as_ref()
+                        .is_some_and(|lobby| lobby.race == snapshot.race)
+                    {
+                        self.snapshot = Some(snapshot);
+                    }
+                }
+                HostMessage::Busy => self.busy = true,
+                HostMessage::Waiting => {}
+                HostMessage::Closed => self.closed = true,
+            }
+        }
+    }
+
+    /// Newest state of the race received since the last call
+    pub fn take_snapshot(&mut self) -> Option<Snapshot> {
+        self.snapshot.take()
+    }
+
+    /// The host was not heard for a while, or it left
+    pub fn disconnected(&self) -> bool {
+        self.closed || self.last_heard.elapsed() > TIMEOUT
+    }
+
+    /// Sends the inputs the host has not acknowledged, or just that the client is still there
+    pub fn send_inputs(&self) {
+        let Some(lobby) = &self.lobby else {
+            return;
+        };
+        let (first, inputs) = self.prediction.unacked();
+        self.send(&ClientMessage::Inputs {
+            race: lobby.race,
+            first,
+            inputs,
+        });
+    }
+
+    /// Keeps the car of the client from its menus between two races
+    pub fn keep_alive(&mut self) {
+        self.receive();
+        // the host only notes that a client it knows is still there
+        self.join();
+    }
+
+    pub fn leave(&mut self) {
+        self.send(&ClientMessage::Leave);
+        self.lobby = None;
+    }
+
+    fn send(&self, message: &ClientMessage) {
+        // a lost datagram is sent again with the next inputs
+        let _ = self.socket.send(&encode(message));
+    }
+}
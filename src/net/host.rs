@@ -0,0 +1,225 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+use super::{decode, encode, ClientMessage, HostMessage, Snapshot, MAX_DATAGRAM, TIMEOUT};
+use crate::sim::{CarStat, PlayerInput, RacerState};
+
+// inputs a client may be ahead of the host before the oldest ones are skipped
+pub(super) const MAX_QUEUED_INPUTS: u32 = 12;
+
+/// Computer of the local network driving a car of the race
+#[derive(Debug)]
+struct RemoteClient {
+    addr: SocketAddr,
+    // inputs received and not applied yet, by number
+    inputs: BTreeMap<u32, PlayerInput>,
+    // number of the next input to apply
+    next: u32,
+    last: PlayerInput,
+    acked: Option<u32>,
+    last_heard: Instant,
+    /// the client left during the race, its car is kept until the next one
+    left: bool,
+}
+
+impl RemoteClient {
+    fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            inputs: BTreeMap::new(),
+            next: 0,
+            last: PlayerInput::default(),
+            acked: None,
+            last_heard: Instant::now(),
+            left: false,
+        }
+    }
+}
+
+/// Runs the race for every computer: the clients send their inputs,
+/// the host simulates every car and sends their states back
+#[derive(Debug)]
+pub struct Host {
+    socket: UdpSocket,
+    clients: Vec<RemoteClient>,
+    /// number of the current race, messages of older races are ignored
+    race: u32,
+    racing: bool,
+}
+
+impl Host {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_nonblocking(true)?;
+        Ok(Self {
+            socket,
+            clients: Vec::new(),
+            race: 0,
+            racing: false,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Number of clients with a car in the race
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Slot on the starting grid of a client, the car of the host being the first one
+    pub fn slot(client: usize) -> usize {
+        client + 1
+    }
+
+    /// The client left during the race
+    pub fn left(&self, client: usize) -> bool {
+        self.clients[client].left
+    }
+
+    /// Waits for the clients of a new race, the ones who left the last race lose their car
+    pub fn open_lobby(&mut self) {
+        self.race += 1;
+        self.racing = false;
+        self.clients.retain(|client| !client.left);
+        for client in &mut self.clients {
+            *client = RemoteClient::new(client.addr);
+        }
+    }
+
+    /// Starts the race, the clients joining later wait for the next one
+    pub fn start(&mut self) {
+        self.racing = true;
+    }
+
+    /// Reads the messages of the clients, and forgets the ones not heard for a while
+    pub fn receive(&mut self) {
+        let mut buffer = vec![0; MAX_DATAGRAM];
+        while let Ok((size, addr)) = self.socket.recv_from(&mut buffer) {
+            if let Some(message) = decode(&buffer[..size]) {
+                self.handle(addr, message);
+            }
+        }
+
+        for client in &mut self.clients {
+            client.left |= client.last_heard.elapsed() > TIMEOUT;
+        }
+        if !self.racing {
+            self.clients.retain(|client| !client.left);
+        }
+    }
+
+    fn handle(&mut self, addr: SocketAddr, message: ClientMessage) {
+        let known = self.clients.iter().position(|client| client.addr == addr);
+        if let Some(index) = known {
+            self.clients[index].last_heard = Instant::now();
+        }
+        match (message, known) {
+            (ClientMessage::Join { version }, None) => {
+                if version != env!("CARGO_PKG_VERSION") {
+                    eprintln!("[WARN] {addr} joined with version {version}");
+                }
+                if self.racing {
+                    self.send(addr, &HostMessage::Busy);
+                } else {
+                    self.clients.push(RemoteClient::new(addr));
+                }
+            }
+            (
+                ClientMessage::Inputs {
+                    race,
+                    first,
+                    inputs,
+                },
+                Some(index),
+            ) if race == self.race => {
+                let client = &mut self.clients[index];
+                for (number, input) in (first..).zip(inputs) {
+                    if number >= client.next {
+                        client.inputs.insert(number, input);
+                    }
+                }
+            }
+            (ClientMessage::Leave, Some(index)) => {
+                if self.racing {
+                    self.clients[index].left = true;
+                } else {
+                    self.clients.remove(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Input of a client for the next simulation step: the next one it sent,
+    /// the last one again if it is late
+    pub fn next_input(&mut self, client: usize) -> PlayerInput {
+        let client = &mut self.clients[client];
+        if client.left {
+            return PlayerInput::default();
+        }
+        // a client too far ahead catches up by skipping its oldest inputs
+        if let Some(newest) = client.inputs.keys().next_back() {
+            client.next = client.next.max(newest.saturating_sub(MAX_QUEUED_INPUTS));
+        }
+        client.inputs = client.inputs.split_off(&client.next);
+        if let Some(input) = client.inputs.remove(&client.next) {
+            client.last = input;
+            client.acked = Some(client.next);
+            client.next += 1;
+        }
+        client.last
+    }
+
+    /// Tells each client its place in the next race
+    pub fn send_lobby(&self, level: &str, stat: CarStat, racers: usize) {
+        for (index, client) in self.clients.iter().enumerate() {
+            let message = HostMessage::Lobby {
+                race: self.race,
+                level: level.to_string(),
+                slot: Self::slot(index),
+                stat,
+                racers,
+            };
+            self.send(client.addr, &message);
+        }
+    }
+
+    /// Sends the state of every car to the clients, by slot
+    pub fn send_state(&self, countdown: f64, racers: &[RacerState], finish_order: &[usize]) {
+        for client in self.clients.iter().filter(|client| !client.left) {
+            let snapshot = Snapshot {
+                race: self.race,
+                countdown,
+                racers: racers.to_vec(),
+                finish_order: finish_order.to_vec(),
+                acked: client.acked,
+            };
+            self.send(client.addr, &HostMessage::State(snapshot));
+        }
+    }
+
+    /// Keeps the clients from the menus of the host between two races
+    pub fn keep_alive(&mut self) {
+        self.receive();
+        for client in self.clients.iter().filter(|client| !client.left) {
+            self.send(client.addr, &HostMessage::Waiting);
+        }
+    }
+
+    /// Tells the clients that the host is gone
+    pub fn close(&mut self) {
+        for client in &self.clients {
+            self.send(client.addr, &HostMessage::Closed);
+        }
+        self.clients.clear();
+    }
+
+    fn send(&self, addr: SocketAddr, message: &HostMessage) {
+        // a lost datagram is sent again with the next state
+        let _ = self.socket.send_to(&encode(message), addr);
+    }
+}
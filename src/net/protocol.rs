@@ -0,0 +1,70 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::sim::{CarStat, PlayerInput, RacerState};
+
+/// Largest datagram read from the socket
+pub const MAX_DATAGRAM: usize = 65_507;
+
+/// Sent by a client to the host
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// asks for a car in the next race, sent again until the host answers
+    Join {
+        version: String,
+    },
+    /// inputs not acknowledged by the host yet, the first one having the number `first`.
+    /// Also sent empty to tell the host that the client is still there.
+    Inputs {
+        race: u32,
+        first: u32,
+        inputs: Vec<PlayerInput>,
+    },
+    Leave,
+}
+
+/// Sent by the host to a client
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum HostMessage {
+    /// next race, waiting for the host to start it
+    Lobby {
+        race: u32,
+        level: String,
+        /// slot of the client on the starting grid
+        slot: usize,
+        stat: CarStat,
+        /// cars of the race, opponents included
+        racers: usize,
+    },
+    State(Snapshot),
+    /// a race is running, the client joins the next one
+    Busy,
+    /// the host is still there, in its menus between two races
+    Waiting,
+    Closed,
+}
+
+/// State of a running race, as simulated by the host
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub race: u32,
+    /// seconds left before the start
+    pub countdown: f64,
+    /// every car of the race, by slot on the starting grid
+    pub racers: Vec<RacerState>,
+    /// slots in the order they crossed the finish line
+    pub finish_order: Vec<usize>,
+    /// last input of the client applied by the host, `None` before the first one
+    pub acked: Option<u32>,
+}
+
+pub fn encode<T: Serialize>(message: &T) -> Vec<u8> {
+    ron::to_string(message)
+        .expect("messages can be serialized")
+        .into_bytes()
+}
+
+/// Reads a message, `None` if the datagram is not one
+pub fn decode<T: DeserializeOwned>(datagram: &[u8]) -> Option<T> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    ron::from_str(text).ok()
+}
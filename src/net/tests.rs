@@ -0,0 +1,193 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use super::*;
+use crate::sim::test_support::{open_field, STAT};
+use crate::sim::{PlayerInput, Racer, SIMULATION_RATE};
+
+const STEP: f64 = 1.0 / SIMULATION_RATE;
+
+/// Accelerates while turning right after a while
+fn input(step: usize) -> PlayerInput {
+    PlayerInput {
+        accelerate: 1.0,
+        turn: if step > 30 { 0.5 } else { 0.0 },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn reconcile_applies_the_unacknowledged_inputs_again() {
+    let track = open_field(3, Vec::new());
+    let mut predicted = Racer::new(STAT, &track);
+    let mut prediction = Prediction::default();
+    for step in 0..60 {
+        prediction.step(&mut predicted, input(step), &track, STEP);
+    }
+
+    // the host saw the first 40 inputs, and the car was bumped meanwhile
+    let mut host = Racer::new(STAT, &track);
+    for step in 0..40 {
+        host.step(&input(step), &track, STEP);
+    }
+    host.car.knockback.x += 2.0;
+    prediction.reconcile(&mut predicted, &host.state(), 39, &track, STEP);
+
+    for step in 40..60 {
+        host.step(&input(step), &track, STEP);
+    }
+    assert_eq!(predicted.state(), host.state());
+    // only the last inputs are left to acknowledge
+    let (first, inputs) = prediction.unacked();
+    assert_eq!(first, 40);
+    assert_eq!(inputs.len(), 20);
+}
+
+#[test]
+fn client_races_with_the_host_on_loopback() {
+    let track = open_field(3, Vec::new());
+    let mut host = Host::bind("127.0.0.1:0").unwrap();
+    let mut client = Client::connect(host.local_addr().unwrap()).unwrap();
+
+    // exchanges datagrams until `done`
+    let wait = |host: &mut Host, client: &mut Client, done: &dyn Fn(&Client) -> bool| {
+        for _ in 0..200 {
+            host.receive();
+            host.send_lobby("field", STAT, 2);
+            client.receive();
+            if done(client) {
+                return;
+            }
+            sleep(Duration::from_millis(5));
+        }
+        panic!("no answer on loopback");
+    };
+    wait(&mut host, &mut client, &|client| client.lobby.is_some());
+    assert_eq!(host.clients(), 1);
+    assert_eq!(client.lobby.as_ref().unwrap().slot, Host::slot(0));
+
+    host.start();
+    let mut predicted = Racer::on_grid(STAT, &track, 1);
+    for step in 0..30 {
+        client
+            .prediction
+            .step(&mut predicted, input(step), &track, STEP);
+    }
+    client.send_inputs();
+
+    // the host applies the inputs as they come
+    let mut remote = Racer::on_grid(STAT, &track, 1);
+    let mut applied = 0;
+    for _ in 0..200 {
+        host.receive();
+        // nothing was sent before the first input arrives
+        let input = host.next_input(0);
+        if input != PlayerInput::default() {
+            remote.step(&input, &track, STEP);
+            applied += 1;
+        }
+        if applied == 30 {
+            break;
+        }
+        sleep(Duration::from_millis(1));
+    }
+    assert_eq!(applied, 30);
+
+    host.send_state(
+        0.0,
+        &[Racer::new(STAT, &track).state(), remote.state()],
+        &[],
+    );
+    let mut snapshot = None;
+    for _ in 0..200 {
+        client.receive();
+        snapshot = client.take_snapshot();
+        if snapshot.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(5));
+    }
+    let snapshot = snapshot.expect("state of the race");
+    assert_eq!(snapshot.acked, Some(29));
+    client.prediction.reconcile(
+        &mut predicted,
+        &snapshot.racers[1],
+        snapshot.acked.unwrap(),
+        &track,
+        STEP,
+    );
+    assert_eq!(predicted.state(), remote.state());
+}
+
+#[test]
+fn inputs_skipped_by_the_host_are_reconciled() {
+    let track = open_field(3, Vec::new());
+    let mut host = Host::bind("127.0.0.1:0").unwrap();
+    let mut client = Client::connect(host.local_addr().unwrap()).unwrap();
+    for _ in 0..200 {
+        host.receive();
+        host.send_lobby("field", STAT, 2);
+        client.receive();
+        if client.lobby.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(5));
+    }
+    host.start();
+
+    // the client is far ahead of the host, which skips its oldest inputs
+    let ahead = MAX_QUEUED_INPUTS as usize + 28;
+    let mut predicted = Racer::on_grid(STAT, &track, 1);
+    for step in 0..ahead {
+        client
+            .prediction
+            .step(&mut predicted, input(step), &track, STEP);
+    }
+    client.send_inputs();
+    let mut remote = Racer::on_grid(STAT, &track, 1);
+    let mut first = PlayerInput::default();
+    for _ in 0..200 {
+        host.receive();
+        first = host.next_input(0);
+        if first != PlayerInput::default() {
+            break;
+        }
+        sleep(Duration::from_millis(5));
+    }
+    assert_eq!(first, input(ahead - 1 - MAX_QUEUED_INPUTS as usize));
+    remote.step(&first, &track, STEP);
+
+    host.send_state(
+        0.0,
+        &[Racer::new(STAT, &track).state(), remote.state()],
+        &[],
+    );
+    let mut snapshot = None;
+    for _ in 0..200 {
+        client.receive();
+        snapshot = client.take_snapshot();
+        if snapshot.is_some() {
+            break;
+        }
+        sleep(Duration::from_millis(5));
+    }
+    let snapshot = snapshot.expect("state of the race");
+    assert_eq!(snapshot.acked, Some((ahead - 1) as u32 - MAX_QUEUED_INPUTS));
+    client.prediction.reconcile(
+        &mut predicted,
+        &snapshot.racers[1],
+        snapshot.acked.unwrap(),
+        &track,
+        STEP,
+    );
+
+    // the prediction follows the car of the host from the inputs it did not skip
+    for _ in 0..MAX_QUEUED_INPUTS {
+        remote.step(&host.next_input(0), &track, STEP);
+    }
+    assert_eq!(predicted.state(), remote.state());
+    assert_eq!(
+        client.prediction.unacked().1.len(),
+        MAX_QUEUED_INPUTS as usize
+    );
+}
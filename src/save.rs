@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::LevelConfig;
+use crate::sim::Replay;
+
+const GAME_DIR: &str = "bigrace";
+const RECORDS_FILE: &str = "records.ron";
+const REPLAYS_DIR: &str = "replays";
+const LEVELS_DIR: &str = "levels";
+// the oldest replays are deleted past this count
+const MAX_REPLAYS: usize = 20;
+
+/// Directory where the game keeps the data of the player
+pub fn data_dir() -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".local/share")))
+    };
+    base.unwrap_or_else(|| PathBuf::from(".")).join(GAME_DIR)
+}
+
+/// State of the car at one simulation step of a lap
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct GhostFrame {
+    pub position: (f32, f32),
+    pub rotation: f32,
+    pub animation: usize,
+}
+
+/// Personal records on a level
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub best_time: Option<f64>,
+    pub best_lap: Option<f64>,
+    /// checkpoint splits of the best lap
+    pub best_splits: Vec<f64>,
+    /// car of the best lap, one frame per simulation step
+    #[serde(default)]
+    pub ghost: Vec<GhostFrame>,
+}
+
+impl LevelRecord {
+    /// Keeps the lap if it is the best one, returns true if it is.
+    pub fn record_lap(&mut self, time: f64, splits: &[f64], ghost: &[GhostFrame]) -> bool {
+        if self.best_lap.is_some_and(|best| best <= time) {
+            return false;
+        }
+        self.best_lap = Some(time);
+        self.best_splits = splits.to_vec();
+        self.ghost = ghost.to_vec();
+        true
+    }
+
+    /// Keeps the race time if it is the best one, returns true if it is.
+    pub fn record_time(&mut self, time: f64) -> bool {
+        if self.best_time.is_some_and(|best| best <= time) {
+            return false;
+        }
+        self.best_time = Some(time);
+        true
+    }
+}
+
+/// Records of every level, by level name
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Records {
+    levels: BTreeMap<String, LevelRecord>,
+}
+
+impl Records {
+    fn path() -> PathBuf {
+        data_dir().join(RECORDS_FILE)
+    }
+
+    /// Reads the records file. A corrupted file is put aside and the records start over.
+    pub fn load() -> Self {
+        Self::load_from(&Self::path())
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let Ok(records_str) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        ron::from_str(&records_str).unwrap_or_else(|err| {
+            let backup = path.with_extension("ron.bak");
+            eprintln!(
+                "[ERR] corrupted {}, moved to {}: {err}",
+                path.display(),
+                backup.display()
+            );
+            let _ = std::fs::rename(path, backup);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        self.save_to(&Self::path());
+    }
+
+    fn save_to(&self, path: &Path) {
+        // the frames of the ghosts are kept on one line
+        let pretty = ron::ser::PrettyConfig::default().depth_limit(3);
+        let records_str = ron::ser::to_string_pretty(self, pretty).expect("serializable records");
+        // written next to the file first, so a crash never leaves a half written file
+        let tmp = path.with_extension("ron.tmp");
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let result = std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(&tmp, records_str))
+            .and_then(|_| std::fs::rename(&tmp, path));
+        if let Err(err) = result {
+            eprintln!("[ERR] could not save {}: {err}", path.display());
+        }
+    }
+
+    pub fn level(&self, name: &str) -> Option<&LevelRecord> {
+        self.levels.get(name)
+    }
+
+    pub fn level_mut(&mut self, name: &str) -> &mut LevelRecord {
+        self.levels.entry(name.to_string()).or_default()
+    }
+
+    pub fn forget(&mut self, name: &str) {
+        self.levels.remove(name);
+    }
+}
+
+/// Directory of the replays, named after their level and the date
+pub fn replays_dir() -> PathBuf {
+    data_dir().join(REPLAYS_DIR)
+}
+
+/// Writes the replay in the replays directory, named after the level and the date,
+/// and deletes the oldest ones past `MAX_REPLAYS`
+pub fn save_replay(replay: &Replay) -> Option<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let dir = replays_dir();
+    let path = dir.join(format!("{}-{secs}.ron", replay.level));
+    let replay_str = ron::to_string(replay).expect("serializable replay");
+    let result = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, replay_str));
+    match result {
+        Ok(()) => {
+            prune_replays(&dir, MAX_REPLAYS);
+            Some(path)
+        }
+        Err(err) => {
+            eprintln!("[ERR] could not save {}: {err}", path.display());
+            None
+        }
+    }
+}
+
+/// Deletes the oldest replays of the directory to only keep `keep` of them
+fn prune_replays(dir: &Path, keep: usize) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut replays: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .filter_map(|path| Some((path.metadata().ok()?.modified().ok()?, path)))
+        .collect();
+    // newest first, the name breaks the ties of replays saved during the same second
+    replays.sort_by(|a, b| b.cmp(a));
+    for (_, path) in replays.into_iter().skip(keep) {
+        if let Err(err) = std::fs::remove_file(&path) {
+            eprintln!("[WARN] could not delete {}: {err}", path.display());
+        }
+    }
+}
+
+/// Deletes the replays of a level from the directory
+fn delete_replays(dir: &Path, level: &str) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let prefix = format!("{level}-");
+    for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+        let is_replay = path
+            .file_stem()
+            .and_then(|stem| stem.to_str()?.strip_prefix(&prefix))
+            .is_some_and(|secs| secs.parse::<u64>().is_ok());
+        if is_replay && path.extension().is_some_and(|extension| extension == "ron") {
+            if let Err(err) = std::fs::remove_file(&path) {
+                eprintln!("[WARN] could not delete {}: {err}", path.display());
+            }
+        }
+    }
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, String> {
+    let replay_str = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    ron::from_str(&replay_str).map_err(|err| err.to_string())
+}
+
+/// Directory of the levels changed in the editor, one file per level named after it
+pub fn edited_levels_dir() -> PathBuf {
+    data_dir().join(LEVELS_DIR)
+}
+
+/// Writes a level edited in the editor in `dir`, returns the path of its file
+pub fn save_level(dir: &Path, level: &LevelConfig) -> Result<PathBuf, String> {
+    let path = dir.join(format!("{}.ron", level.name()));
+    let level_str = ron::to_string(level).map_err(|err| err.to_string())?;
+    std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&path, level_str))
+        .map_err(|err| format!("could not save {}: {err}", path.display()))?;
+    Ok(path)
+}
+
+/// Deletes the edited copy of a level from `dir`, for the level to be played as shipped
+pub fn delete_level(dir: &Path, name: &str) -> Result<(), String> {
+    let path = dir.join(format!("{name}.ron"));
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("could not delete {}: {err}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Forgets the records, the ghost and the replays of a level whose tiles changed,
+/// as they were driven on the old tiles
+pub fn forget_level(records: &mut Records, name: &str) {
+    records.forget(name);
+    records.save();
+    delete_replays(&replays_dir(), name);
+}
+
+/// Replaces the levels of `levels.ron` by their edited copy in `dir`, if they have one
+pub fn load_edited_levels(dir: &Path, levels: &mut [LevelConfig]) {
+    for level in levels {
+        let path = dir.join(format!("{}.ron", level.name()));
+        let Ok(level_str) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        match ron::from_str(&level_str) {
+            Ok(edited) => *level = edited,
+            Err(err) => eprintln!("[WARN] edited level {} ignored: {err}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_support;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+
+use super::GAME_DIR;
+
+/// Empty directory of the system temp dir, for the files of one test
+pub fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{GAME_DIR}-{test}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
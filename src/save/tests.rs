@@ -0,0 +1,190 @@
+use super::test_support::temp_dir;
+use super::*;
+use crate::game::Levels;
+use crate::sim::{Rotation, Tile, TileType};
+
+fn shipped_levels() -> Vec<LevelConfig> {
+    let levels = std::fs::read_to_string("assets/levels/levels.ron").unwrap();
+    ron::from_str::<Levels>(&levels).unwrap().levels
+}
+
+#[test]
+fn saved_level_replaces_the_shipped_one() {
+    let dir = temp_dir("levels");
+    let mut levels = shipped_levels();
+    let mut tiles = levels[1].tiles().to_vec();
+    tiles.push(Tile {
+        position: [0, 0],
+        tile_type: TileType::Base6,
+        rotation: Rotation::Pi,
+    });
+    let edited = levels[1].with_tiles([3, 4], tiles.clone());
+
+    let path = save_level(&dir, &edited).unwrap();
+    assert_eq!(path, dir.join(format!("{}.ron", levels[1].name())));
+    // a file that can't be read leaves its level as shipped
+    std::fs::write(dir.join(format!("{}.ron", levels[0].name())), "(name:").unwrap();
+
+    load_edited_levels(&dir, &mut levels);
+    let shipped = shipped_levels();
+    assert_eq!(levels[0].tiles(), shipped[0].tiles());
+    assert_eq!(levels[1].name(), shipped[1].name());
+    assert_eq!(levels[1].starting_position(), [3, 4]);
+    assert_eq!(levels[1].tiles(), tiles.as_slice());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn reverted_level_is_played_as_shipped() {
+    let dir = temp_dir("revert");
+    let mut levels = shipped_levels();
+    let edited = levels[0].with_tiles([3, 4], levels[0].tiles().to_vec());
+    save_level(&dir, &edited).unwrap();
+
+    delete_level(&dir, edited.name()).unwrap();
+    // a level without an edited copy is already as shipped
+    delete_level(&dir, edited.name()).unwrap();
+    load_edited_levels(&dir, &mut levels);
+    assert!(levels[0].same_layout(&shipped_levels()[0]));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_the_replays_of_the_level_are_deleted() {
+    let dir = temp_dir("level_replays");
+    for name in [
+        "test-1.ron",
+        "test-2.ron",
+        "test-track-3.ron",
+        "original-4.ron",
+    ] {
+        std::fs::write(dir.join(name), "").unwrap();
+    }
+
+    delete_replays(&dir, "test");
+    let mut left: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    left.sort();
+    assert_eq!(left, ["original-4.ron", "test-track-3.ron"]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+fn frame(x: f32) -> GhostFrame {
+    GhostFrame {
+        position: (x, 0.0),
+        rotation: 0.0,
+        animation: 0,
+    }
+}
+
+#[test]
+fn only_better_times_replace_the_records() {
+    let mut record = LevelRecord::default();
+    assert!(record.record_lap(30.0, &[10.0, 20.0], &[frame(1.0)]));
+    // a slower or equal lap keeps the splits and the ghost of the best one
+    assert!(!record.record_lap(31.0, &[9.0, 21.0], &[frame(2.0)]));
+    assert!(!record.record_lap(30.0, &[9.0, 21.0], &[frame(2.0)]));
+    assert_eq!(record.best_lap, Some(30.0));
+    assert_eq!(record.best_splits, vec![10.0, 20.0]);
+    assert_eq!(record.ghost[0].position, (1.0, 0.0));
+
+    assert!(record.record_lap(29.5, &[9.5], &[frame(3.0), frame(4.0)]));
+    assert_eq!(record.best_lap, Some(29.5));
+    assert_eq!(record.best_splits, vec![9.5]);
+    assert_eq!(record.ghost.len(), 2);
+
+    assert!(record.record_time(95.0));
+    assert!(!record.record_time(95.0));
+    assert!(!record.record_time(100.0));
+    assert!(record.record_time(90.0));
+    assert_eq!(record.best_time, Some(90.0));
+
+    let mut records = Records::default();
+    *records.level_mut("original") = record;
+    records.forget("original");
+    assert!(records.level("original").is_none());
+}
+
+#[test]
+fn records_are_read_back_after_saving() {
+    let dir = temp_dir("records");
+    let path = dir.join("nested").join(RECORDS_FILE);
+    assert!(Records::load_from(&path).level("original").is_none());
+
+    let mut records = Records::default();
+    let record = records.level_mut("original");
+    record.record_lap(30.0, &[10.0], &[frame(1.0), frame(2.0)]);
+    record.record_time(95.0);
+    // a file half written by an earlier crash is replaced
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path.with_extension("ron.tmp"), "(levels:{").unwrap();
+    records.save_to(&path);
+
+    assert!(!path.with_extension("ron.tmp").exists());
+    let loaded = Records::load_from(&path);
+    let record = loaded.level("original").unwrap();
+    assert_eq!(record.best_lap, Some(30.0));
+    assert_eq!(record.best_time, Some(95.0));
+    assert_eq!(record.best_splits, vec![10.0]);
+    assert_eq!(record.ghost.len(), 2);
+
+    // saving again replaces the whole file
+    let mut records = Records::default();
+    records.level_mut("test").record_time(50.0);
+    records.save_to(&path);
+    let loaded = Records::load_from(&path);
+    assert!(loaded.level("original").is_none());
+    assert_eq!(loaded.level("test").unwrap().best_time, Some(50.0));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn corrupted_records_are_moved_aside() {
+    let dir = temp_dir("corrupted");
+    let path = dir.join(RECORDS_FILE);
+    std::fs::write(&path, "(levels:{\"original\":(best_time:").unwrap();
+
+    let records = Records::load_from(&path);
+    assert!(records.level("original").is_none());
+    assert!(!path.exists());
+    let backup = std::fs::read_to_string(path.with_extension("ron.bak")).unwrap();
+    assert_eq!(backup, "(levels:{\"original\":(best_time:");
+
+    // the records start over in a new file
+    records.save_to(&path);
+    assert!(Records::load_from(&path).level("original").is_none());
+    assert!(path.exists());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn only_the_newest_replays_are_kept() {
+    let dir = temp_dir("replays");
+    let start = SystemTime::now() - std::time::Duration::from_secs(100);
+    for i in 0..5u64 {
+        let path = dir.join(format!("original-{i}.ron"));
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_modified(start + std::time::Duration::from_secs(i))
+            .unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "kept").unwrap();
+
+    prune_replays(&dir, 3);
+    let mut left: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    left.sort();
+    assert_eq!(
+        left,
+        [
+            "notes.txt",
+            "original-2.ron",
+            "original-3.ron",
+            "original-4.ron"
+        ]
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
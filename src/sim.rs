@@ -0,0 +1,35 @@
+mod ai;
+mod car;
+mod checkpoint;
+mod collision;
+mod input;
+mod race;
+mod racer;
+mod racing_line;
+mod replay;
+mod respawn;
+mod track;
+mod track_graph;
+
+pub use ai::*;
+pub use car::*;
+pub use checkpoint::*;
+pub use collision::*;
+pub use input::*;
+pub use race::*;
+pub use racer::*;
+pub use racing_line::*;
+pub use replay::*;
+pub use respawn::*;
+pub use track::*;
+pub use track_graph::*;
+
+/// Number of simulation steps per second
+pub const SIMULATION_RATE: f64 = 120.0;
+/// Frame rate the car stats were tuned for
+pub const REFERENCE_RATE: f32 = 60.0;
+
+#[cfg(test)]
+pub mod test_support;
+#[cfg(test)]
+mod tests;
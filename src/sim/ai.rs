@@ -0,0 +1,193 @@
+use std::f32::consts::FRAC_PI_2;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Car, PlayerInput, RacingLine, REFERENCE_RATE, TILE_SIZE};
+
+// distance of the point the car steers to
+const LOOKAHEAD: f32 = 2.5 * TILE_SIZE;
+// distance the car looks for turns to slow down before them
+const BRAKING_LOOKAHEAD: f32 = 20.0 * TILE_SIZE;
+// distance lost by the velocity while coasting, for a velocity of 1
+const COASTING_DISTANCE: f32 = 50.0;
+// velocity used to steer a car too slow to turn much
+const MIN_STEERING_VELOCITY: f32 = 1.0;
+// seconds without moving before the car asks to be put back on the track
+const STUCK_DELAY: f32 = 2.0;
+// a car further from its waypoint is looked for on the whole line, after a respawn
+const LOST_DISTANCE: f32 = 6.0 * TILE_SIZE;
+
+/// How well the AI cars drive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+struct Skill {
+    /// part of the max velocity reached on the straights
+    top_speed: f32,
+    /// part of the velocity the car could take the turns at
+    cornering: f32,
+    boost: bool,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+        }
+    }
+
+    fn skill(self) -> Skill {
+        match self {
+            Difficulty::Easy => Skill {
+                top_speed: 0.75,
+                cornering: 0.7,
+                boost: false,
+            },
+            Difficulty::Medium => Skill {
+                top_speed: 0.9,
+                cornering: 0.85,
+                boost: true,
+            },
+            Difficulty::Hard => Skill {
+                top_speed: 1.0,
+                cornering: 1.0,
+                boost: true,
+            },
+        }
+    }
+}
+
+/// Drives a car along a racing line, slowing down before the turns
+#[derive(Clone, Debug)]
+pub struct AiDriver {
+    pub difficulty: Difficulty,
+    // waypoint nearest to the car
+    waypoint: Option<usize>,
+    // time spent without moving
+    stuck: f32,
+}
+
+impl AiDriver {
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            difficulty,
+            waypoint: None,
+            stuck: 0.0,
+        }
+    }
+
+    /// Input for the next simulation step of the car
+    pub fn input(&mut self, car: &Car, line: &RacingLine, delta_time: f32) -> PlayerInput {
+        if line.len() < 3 {
+            return PlayerInput::default();
+        }
+        let skill = self.difficulty.skill();
+        let waypoint = self.follow(car, line);
+
+        // steer towards a point a little ahead on the line
+        let mut target = waypoint + 1;
+        while car.position.distance(line.point(target)) < LOOKAHEAD
+            && target < waypoint + line.len()
+        {
+            target += 1;
+        }
+        // at the lookahead distance between the last waypoint inside of it and the first outside
+        let (inside, outside) = (line.point(target - 1), line.point(target));
+        let (near, far) = (
+            car.position.distance(inside),
+            car.position.distance(outside),
+        );
+        let along = if far > near {
+            ((LOOKAHEAD - near) / (far - near)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let heading = car.heading();
+        let direction = inside.lerp(outside, along) - car.position;
+        let angle = heading.perp_dot(direction).atan2(heading.dot(direction));
+        // turn along the arc going through the target, the tighter the slower the car is
+        let curvature = 2.0 * angle.sin() / direction.length().max(1.0);
+        let turn = if angle.abs() > FRAC_PI_2 {
+            angle.signum()
+        } else {
+            (curvature * car.velocity.max(MIN_STEERING_VELOCITY) * REFERENCE_RATE
+                / car.stat.rotation_speed)
+                .clamp(-1.0, 1.0)
+        };
+
+        // the fastest velocity that still lets the car slow down enough before each turn
+        let mut speed = car.stat.max_velocity * skill.top_speed;
+        let mut distance = car.position.distance(line.point(waypoint));
+        let mut index = waypoint;
+        while distance < BRAKING_LOOKAHEAD && index < waypoint + line.len() {
+            let corner = self.corner_velocity(car, line, index) * skill.cornering;
+            speed = speed.min(corner + distance / COASTING_DISTANCE);
+            distance += line.point(index).distance(line.point(index + 1));
+            index += 1;
+        }
+
+        self.stuck = if car.velocity < 0.5 {
+            self.stuck + delta_time
+        } else {
+            0.0
+        };
+        let reset = self.stuck > STUCK_DELAY;
+        if reset {
+            self.stuck = 0.0;
+        }
+
+        let straight = angle.abs() < 0.1 && speed >= car.stat.max_velocity * skill.top_speed;
+        PlayerInput {
+            accelerate: if car.velocity < speed { 1.0 } else { 0.0 },
+            brake: if car.velocity > speed * 1.1 { 1.0 } else { 0.0 },
+            turn,
+            boost: skill.boost && straight && (car.boost >= 1.0 || car.boosting),
+            reset,
+        }
+    }
+
+    // updates the waypoint nearest to the car, looking ahead of the last one
+    fn follow(&mut self, car: &Car, line: &RacingLine) -> usize {
+        let len = line.len();
+        let waypoint = match self.waypoint {
+            Some(last) => (0..12)
+                .map(|ahead| (last + len - 2 + ahead) % len)
+                .min_by(|a, b| {
+                    car.position
+                        .distance_squared(line.point(*a))
+                        .total_cmp(&car.position.distance_squared(line.point(*b)))
+                })
+                .filter(|nearest| car.position.distance(line.point(*nearest)) < LOST_DISTANCE)
+                .unwrap_or_else(|| line.nearest(car.position)),
+            None => line.nearest(car.position),
+        };
+        self.waypoint = Some(waypoint);
+        waypoint
+    }
+
+    // velocity at which the car can turn as much as the line around a waypoint
+    fn corner_velocity(&self, car: &Car, line: &RacingLine, index: usize) -> f32 {
+        let len = line.len();
+        let (previous, point, next) = (
+            line.point(index + len - 1),
+            line.point(index),
+            line.point(index + 1),
+        );
+        let (a, b) = (point - previous, next - point);
+        let angle = a.perp_dot(b).atan2(a.dot(b)).abs();
+        if angle < 1e-3 {
+            return f32::MAX;
+        }
+        let radius = (a.length() + b.length()) * 0.5 / angle;
+        radius * car.stat.rotation_speed / REFERENCE_RATE
+    }
+}
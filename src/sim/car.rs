@@ -0,0 +1,320 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Border, Collider, Contact, PlayerInput, RectHitbox, RespawnPoint, Track, REFERENCE_RATE,
+};
+
+// maximum number of contacts resolved in one step
+const CONTACT_ITERATIONS: usize = 4;
+// knockback lost per reference frame
+const KNOCKBACK_DECAY: f32 = 0.9;
+/// Seconds during which a respawned car can neither crash nor be slowed by walls
+pub const RESPAWN_INVULNERABILITY: f32 = 2.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CarStat {
+    pub max_velocity: f32,
+    pub rotation_speed: f32,
+    pub acceleration: f32,
+    pub brake: f32,
+    pub hitbox_size: (f32, f32),
+    /// part of the speed going into a wall given back by the bounce
+    #[serde(default = "default_restitution")]
+    pub restitution: f32,
+    /// part of the speed along a wall lost per reference frame while touching it
+    #[serde(default = "default_wall_friction")]
+    pub wall_friction: f32,
+    /// part of the speed lost when hitting a wall head-on at max velocity
+    #[serde(default)]
+    pub wall_penalty: f32,
+    /// part of the boost meter filled per second when not boosting
+    #[serde(default = "default_boost_charge")]
+    pub boost_charge: f32,
+    /// part of the boost meter used per second of boost
+    #[serde(default = "default_boost_drain")]
+    pub boost_drain: f32,
+    /// acceleration added while boosting
+    #[serde(default = "default_boost_acceleration")]
+    pub boost_acceleration: f32,
+    /// max velocity added while boosting
+    #[serde(default = "default_boost_velocity")]
+    pub boost_velocity: f32,
+    /// radius of the circle other cars bump into
+    #[serde(default = "default_collision_radius")]
+    pub collision_radius: f32,
+}
+
+fn default_restitution() -> f32 {
+    0.5
+}
+
+fn default_wall_friction() -> f32 {
+    0.02
+}
+
+fn default_boost_charge() -> f32 {
+    0.1
+}
+
+fn default_boost_drain() -> f32 {
+    0.5
+}
+
+fn default_boost_acceleration() -> f32 {
+    10.0
+}
+
+fn default_boost_velocity() -> f32 {
+    4.0
+}
+
+fn default_collision_radius() -> f32 {
+    8.0
+}
+
+#[derive(Clone, Debug)]
+pub struct Car {
+    pub position: Vec2,
+    pub rotation: f32,
+    /// expressed in pixels per reference frame
+    pub velocity: f32,
+    /// speed given by the walls, not along the heading of the car
+    pub knockback: Vec2,
+    /// the car left the tile map
+    pub crashed: bool,
+    /// normal of the wall hit during the last step
+    pub wall_hit: Option<Vec2>,
+    /// seconds left before the car can crash again
+    pub invulnerable: f32,
+    /// boost meter in [0, 1]
+    pub boost: f32,
+    /// the boost was used during the last step
+    pub boosting: bool,
+
+    // state before the last step
+    pub previous_position: Vec2,
+    pub previous_rotation: f32,
+
+    pub stat: CarStat,
+}
+
+impl Car {
+    pub fn new(stat: CarStat) -> Self {
+        Self {
+            position: Vec2::new(0.0, 0.0),
+            rotation: 0.0,
+            velocity: 0.0,
+            knockback: Vec2::ZERO,
+            crashed: false,
+            wall_hit: None,
+            invulnerable: 0.0,
+            boost: 0.0,
+            boosting: false,
+            previous_position: Vec2::new(0.0, 0.0),
+            previous_rotation: 0.0,
+            stat,
+        }
+    }
+
+    /// Places the car at the center of the tile, facing up.
+    pub fn init(&mut self, pos: [usize; 2]) {
+        self.start_at(RespawnPoint::tile(pos));
+    }
+
+    /// Places the car for the start of a race
+    pub fn start_at(&mut self, point: RespawnPoint) {
+        self.place(point);
+        self.invulnerable = 0.0;
+        self.boost = 0.0;
+    }
+
+    /// Places the car at a respawn point, stopped and invulnerable for a while.
+    pub fn respawn(&mut self, point: RespawnPoint) {
+        self.place(point);
+        self.invulnerable = RESPAWN_INVULNERABILITY;
+    }
+
+    fn place(&mut self, point: RespawnPoint) {
+        self.position = point.position;
+        self.rotation = point.rotation;
+        self.velocity = 0.0;
+        self.knockback = Vec2::ZERO;
+        self.crashed = false;
+        self.wall_hit = None;
+        self.boosting = false;
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+    }
+
+    pub fn movement(&mut self, input: &PlayerInput, delta_time: f32) {
+        self.boosting = input.boost && self.boost > 0.0;
+        let max_velocity = if self.boosting {
+            self.boost = (self.boost - self.stat.boost_drain * delta_time).max(0.0);
+            self.velocity += self.stat.boost_acceleration * delta_time;
+            self.stat.max_velocity + self.stat.boost_velocity
+        } else {
+            self.boost = (self.boost + self.stat.boost_charge * delta_time).min(1.0);
+            // after a boost, the car slows down with the drag
+            self.stat.max_velocity.max(self.velocity)
+        };
+
+        self.velocity += self.stat.acceleration * input.accelerate * delta_time;
+        self.velocity -= self.stat.brake * input.brake * delta_time;
+        self.rotation += self.stat.rotation_speed * input.turn * delta_time;
+
+        // Avoid velocity to get higher than max speed
+        self.velocity = self.velocity.clamp(0.0, max_velocity);
+        let frames = delta_time * REFERENCE_RATE;
+        // Move car
+        self.position += (self.heading() * self.velocity + self.knockback) * frames;
+        // Decelerate car
+        self.velocity *= 0.98_f32.powf(frames);
+        self.knockback *= KNOCKBACK_DECAY.powf(frames);
+    }
+
+    pub fn heading(&self) -> Vec2 {
+        vec2(self.rotation.sin(), -self.rotation.cos())
+    }
+
+    /// Movement of the car per reference frame, along its heading and from the knockback
+    pub fn speed(&self) -> Vec2 {
+        self.heading() * self.velocity + self.knockback
+    }
+
+    // the car keeps the part of the speed along its heading, the rest is knockback
+    fn set_speed(&mut self, speed: Vec2) {
+        let heading = self.heading();
+        self.velocity = speed.dot(heading).max(0.0);
+        self.knockback = speed - heading * self.velocity;
+    }
+
+    pub fn invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    /// Advances the car by one simulation step
+    pub fn step(&mut self, input: &PlayerInput, track: &Track, delta_time: f32) {
+        self.previous_position = self.position;
+        self.previous_rotation = self.rotation;
+        self.wall_hit = None;
+
+        if self.crashed {
+            return;
+        }
+        self.invulnerable = (self.invulnerable - delta_time).max(0.0);
+        self.movement(input, delta_time);
+
+        for _ in 0..CONTACT_ITERATIONS {
+            let Some(contact) = self.contact(track) else {
+                break;
+            };
+            // push the car out of the wall
+            self.position += contact.normal * (contact.depth + 0.01);
+            self.bounce(contact.normal, delta_time);
+            self.wall_hit.get_or_insert(contact.normal);
+        }
+
+        self.crashed = !self.invulnerable() && self.collides(track);
+    }
+
+    /// Deepest contact between the hitbox and the borders of the track
+    fn contact(&self, track: &Track) -> Option<Contact> {
+        self.points()
+            .iter()
+            .filter_map(|point| {
+                let tile = track.tile_at(*point)?;
+                // the smallest push is enough to get the point out of the tile borders
+                Border::from_tile(tile)
+                    .filter_map(|border| border.contact(*point))
+                    .min_by(|a, b| a.depth.total_cmp(&b.depth))
+            })
+            .max_by(|a, b| a.depth.total_cmp(&b.depth))
+    }
+
+    /// Reflects the speed going into the wall and slows down the car along it.
+    fn bounce(&mut self, normal: Vec2, delta_time: f32) {
+        let heading = self.heading();
+        let speed = self.speed();
+        let into_wall = speed.dot(normal);
+        if into_wall >= 0.0 {
+            return;
+        }
+
+        let frames = delta_time * REFERENCE_RATE;
+        let along_wall =
+            (speed - normal * into_wall) * (1.0 - self.stat.wall_friction).powf(frames);
+        let speed = along_wall - normal * into_wall * self.stat.restitution;
+
+        // the harder the hit, the more of the speed along the heading is lost
+        let penalty = if self.invulnerable() {
+            0.0
+        } else {
+            self.stat.wall_penalty * (-into_wall / self.stat.max_velocity).min(1.0)
+        };
+        self.set_speed(speed - heading * speed.dot(heading).max(0.0) * penalty);
+    }
+
+    /// Pushes two overlapping cars away from each other and bounces them off each other.
+    /// Returns whether they touched.
+    pub fn collide(&mut self, other: &mut Car) -> bool {
+        let offset = other.position - self.position;
+        let min_distance = self.stat.collision_radius + other.stat.collision_radius;
+        let distance = offset.length();
+        if distance >= min_distance {
+            return false;
+        }
+        // cars on top of each other are pushed sideways
+        let normal = if distance > 0.0 {
+            offset / distance
+        } else {
+            self.heading().perp()
+        };
+        let push = (min_distance - distance) * 0.5;
+        self.position -= normal * push;
+        other.position += normal * push;
+
+        let (speed, other_speed) = (self.speed(), other.speed());
+        let closing = (speed - other_speed).dot(normal);
+        if closing > 0.0 {
+            // both cars weigh the same
+            let restitution = (self.stat.restitution + other.stat.restitution) * 0.5;
+            let impulse = normal * closing * (1.0 + restitution) * 0.5;
+            self.set_speed(speed - impulse);
+            other.set_speed(other_speed + impulse);
+        }
+        true
+    }
+
+    /// Position and rotation between the last two steps, `alpha` being in [0, 1]
+    pub fn interpolate(&self, alpha: f32) -> (Vec2, f32) {
+        (
+            self.previous_position.lerp(self.position, alpha),
+            self.previous_rotation + (self.rotation - self.previous_rotation) * alpha,
+        )
+    }
+}
+
+impl RectHitbox for Car {
+    fn rect(&self) -> Rect {
+        Rect::new(
+            self.position.x,
+            self.position.y,
+            self.stat.hitbox_size.0,
+            self.stat.hitbox_size.1,
+        )
+    }
+
+    fn rotation(&self) -> f32 {
+        self.rotation
+    }
+}
+
+/// The car is off the tile map
+impl Collider<&Track> for Car {
+    fn collides(&self, other: &Track) -> bool {
+        self.points()
+            .iter()
+            .any(|point| other.tile_at(*point).is_none())
+    }
+}
@@ -0,0 +1,140 @@
+use macroquad::math::{vec2, Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use super::{RespawnPoint, TILE_SIZE};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Gate {
+    /// rectangle of tiles, from its top left tile and its size in tiles
+    Tiles {
+        position: [usize; 2],
+        size: [usize; 2],
+    },
+    /// segment between two points expressed in tiles
+    Line { start: (f32, f32), end: (f32, f32) },
+}
+
+impl Gate {
+    /// Checks if the car went through the gate while moving from `from` to `to`.
+    pub fn passed(&self, from: Vec2, to: Vec2) -> bool {
+        match *self {
+            Gate::Tiles { position, size } => Rect::new(
+                position[0] as f32 * TILE_SIZE,
+                position[1] as f32 * TILE_SIZE,
+                size[0] as f32 * TILE_SIZE,
+                size[1] as f32 * TILE_SIZE,
+            )
+            .contains(to),
+            Gate::Line { start, end } => segments_intersect(
+                from,
+                to,
+                vec2(start.0, start.1) * TILE_SIZE,
+                vec2(end.0, end.1) * TILE_SIZE,
+            ),
+        }
+    }
+
+    /// Middle of the gate, in pixels
+    pub fn center(&self) -> Vec2 {
+        match *self {
+            Gate::Tiles { position, size } => {
+                (vec2(position[0] as f32, position[1] as f32)
+                    + vec2(size[0] as f32, size[1] as f32) * 0.5)
+                    * TILE_SIZE
+            }
+            Gate::Line { start, end } => {
+                (vec2(start.0, start.1) + vec2(end.0, end.1)) * 0.5 * TILE_SIZE
+            }
+        }
+    }
+
+    /// Where to respawn a car that went through the gate from `from` to `to`.
+    /// A line is faced along its normal, tiles along the movement of the car.
+    pub fn respawn_point(&self, from: Vec2, to: Vec2) -> RespawnPoint {
+        let movement = to - from;
+        let direction = match *self {
+            Gate::Tiles { .. } => movement,
+            Gate::Line { start, end } => {
+                let normal = vec2(end.0 - start.0, end.1 - start.1).perp();
+                if normal.dot(movement) < 0.0 {
+                    -normal
+                } else {
+                    normal
+                }
+            }
+        };
+        RespawnPoint::facing(self.center(), direction)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub index: usize,
+    pub gate: Gate,
+}
+
+/// Keeps track of the checkpoints passed during the current lap.
+/// Checkpoints only count when they are passed in the order of their index.
+#[derive(Clone, Debug)]
+pub struct CheckpointTracker {
+    checkpoints: Vec<Checkpoint>,
+    next: usize,
+    /// time since the beginning of the lap at which each checkpoint was passed
+    pub splits: Vec<f64>,
+}
+
+impl CheckpointTracker {
+    pub fn new(checkpoints: &[Checkpoint]) -> Self {
+        let mut checkpoints = checkpoints.to_vec();
+        checkpoints.sort_by_key(|checkpoint| checkpoint.index);
+
+        Self {
+            splits: Vec::with_capacity(checkpoints.len()),
+            checkpoints,
+            next: 0,
+        }
+    }
+
+    /// Checkpoint at a position in the order of the lap
+    pub fn get(&self, position: usize) -> Option<&Checkpoint> {
+        self.checkpoints.get(position)
+    }
+
+    pub fn count(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// All the checkpoints of the lap were passed
+    pub fn completed(&self) -> bool {
+        self.next == self.checkpoints.len()
+    }
+
+    /// Returns the position of the checkpoint if the next one was passed.
+    pub fn update(&mut self, from: Vec2, to: Vec2, lap_time: f64) -> Option<usize> {
+        let checkpoint = self.checkpoints.get(self.next)?;
+        if !checkpoint.gate.passed(from, to) {
+            return None;
+        }
+        self.splits.push(lap_time);
+        self.next += 1;
+        Some(self.next - 1)
+    }
+
+    /// Starts a new lap and returns the splits of the previous one.
+    pub fn new_lap(&mut self) -> Vec<f64> {
+        self.next = 0;
+        std::mem::replace(&mut self.splits, Vec::with_capacity(self.checkpoints.len()))
+    }
+}
+
+fn segments_intersect(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let r = p2 - p1;
+    let s = q2 - q1;
+    let denom = r.perp_dot(s);
+    if denom == 0.0 {
+        return false;
+    }
+    let t = (q1 - p1).perp_dot(s) / denom;
+    let u = (q1 - p1).perp_dot(r) / denom;
+    (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)
+}
@@ -0,0 +1,253 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use macroquad::math::{vec2, Rect, Vec2};
+
+use super::{Tile, TileType, TILE_SIZE};
+
+pub trait Collider<T> {
+    fn collides(&self, other: T) -> bool;
+}
+
+/// Straight border of a tile, the track is on the left of the line when
+/// going from `start` in the direction given by `rotation`.
+#[derive(Debug, Clone, Copy)]
+pub struct LineBorder {
+    pub start: Vec2,
+    pub lengh: f32,
+    // rotation around start
+    pub rotation: f32,
+}
+
+impl LineBorder {
+    pub fn new(start: Vec2, lengh: f32, rotation: f32) -> Self {
+        Self {
+            start,
+            lengh,
+            rotation,
+        }
+    }
+
+    pub fn between(start: Vec2, end: Vec2) -> Self {
+        let direction = end - start;
+        Self::new(start, direction.length(), direction.x.atan2(-direction.y))
+    }
+
+    pub fn rotated_start(&self) -> Vec2 {
+        vec2(
+            self.start.x * self.rotation.cos() + self.start.y * self.rotation.sin(),
+            -self.start.x * self.rotation.sin() + self.start.y * self.rotation.cos(),
+        )
+    }
+
+    pub fn direction(&self) -> Vec2 {
+        vec2(self.rotation.sin(), -self.rotation.cos())
+    }
+
+    pub fn end(&self) -> Vec2 {
+        self.start + self.direction() * self.lengh
+    }
+}
+
+/// Quarter of a circle going clockwise from `start_angle`, the track is inside of it.
+#[derive(Debug, Clone, Copy)]
+pub struct ArcBorder {
+    pub center: Vec2,
+    pub radius: f32,
+    pub start_angle: f32,
+}
+
+/// Where a point went through a border
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    /// unit vector pointing to the track
+    pub normal: Vec2,
+    /// distance to get back on the track
+    pub depth: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Border {
+    Line(LineBorder),
+    Arc(ArcBorder),
+}
+
+/// Borders of a tile before its rotation, in pixels from its top left corner
+enum Shape {
+    Line(Vec2, Vec2),
+    /// center, radius and start angle
+    Arc(Vec2, f32, f32),
+}
+
+const fn line(start: (f32, f32), end: (f32, f32)) -> Shape {
+    Shape::Line(Vec2::new(start.0, start.1), Vec2::new(end.0, end.1))
+}
+
+// shapes follow the transparent parts of the tiles in mapatlas.png
+const STRAIGHT_BORDER: &[Shape] = &[line((0.0, 21.0), (24.0, 21.0))];
+const DIAG_BORDER: &[Shape] = &[line((24.0, 0.0), (0.0, 24.0))];
+const HARD_TURN_INTERIOR: &[Shape] = &[
+    line((20.0, 24.0), (20.0, 20.0)),
+    line((20.0, 20.0), (24.0, 20.0)),
+];
+const HARD_TURN_EXTERIOR: &[Shape] = &[
+    line((3.0, 15.0), (3.0, 24.0)),
+    line((24.0, 3.0), (15.0, 3.0)),
+    Shape::Arc(Vec2::new(15.0, 15.0), 12.0, PI),
+];
+const SOFT_TURN_INTERIOR: &[Shape] = &[line((24.0, 4.0), (8.0, 2.5)), line((8.0, 2.5), (0.0, 0.0))];
+const SOFT_TURN_INTERIOR_2: &[Shape] = &[
+    line((24.0, 0.0), (16.0, 2.5)),
+    line((16.0, 2.5), (0.0, 4.0)),
+];
+const SOFT_TURN_EXTERIOR: &[Shape] = &[
+    line((0.0, 0.0), (24.0, 24.0)),
+    line((0.0, 20.0), (24.0, 20.0)),
+];
+const SOFT_TURN_EXTERIOR_2: &[Shape] = &[
+    line((0.0, 24.0), (24.0, 0.0)),
+    line((0.0, 20.0), (24.0, 20.0)),
+];
+
+impl TileType {
+    /// The tile has no wall, the whole tile is drivable
+    pub fn is_open(self) -> bool {
+        self.shapes().is_empty()
+    }
+
+    fn shapes(self) -> &'static [Shape] {
+        match self {
+            TileType::StraightBorder => STRAIGHT_BORDER,
+            TileType::DiagBorder => DIAG_BORDER,
+            TileType::HardTurnInterior => HARD_TURN_INTERIOR,
+            TileType::HardTurnExterior => HARD_TURN_EXTERIOR,
+            TileType::SoftTurnInterior => SOFT_TURN_INTERIOR,
+            TileType::SoftTurnInterior2 => SOFT_TURN_INTERIOR_2,
+            TileType::SoftTurnExterior => SOFT_TURN_EXTERIOR,
+            TileType::SoftTurnExterior2 => SOFT_TURN_EXTERIOR_2,
+            TileType::StartingLine
+            | TileType::Base1
+            | TileType::Base2
+            | TileType::Base3
+            | TileType::Base4
+            | TileType::Base5
+            | TileType::Base6 => &[],
+        }
+    }
+}
+
+impl Border {
+    pub fn contact(&self, point: Vec2) -> Option<Contact> {
+        if !point.collides(*self) {
+            return None;
+        }
+        match self {
+            Border::Line(border) => {
+                let outside = border.direction().perp();
+                Some(Contact {
+                    normal: -outside,
+                    depth: (point - border.start).dot(outside),
+                })
+            }
+            Border::Arc(border) => {
+                let offset = point - border.center;
+                Some(Contact {
+                    normal: -offset.normalize_or_zero(),
+                    depth: offset.length() - border.radius,
+                })
+            }
+        }
+    }
+
+    /// Borders of the tile in world coordinates
+    pub fn from_tile(tile: &Tile) -> impl Iterator<Item = Border> + '_ {
+        let origin = vec2(tile.position[0] as f32, tile.position[1] as f32) * TILE_SIZE;
+        // tiles are rotated clockwise around their center
+        let quarters = tile.rotation as usize;
+        let rotate = move |point: Vec2| {
+            let center = Vec2::splat(TILE_SIZE * 0.5);
+            let p = point - center;
+            let rotated = match quarters {
+                1 => vec2(-p.y, p.x),
+                2 => -p,
+                3 => vec2(p.y, -p.x),
+                _ => p,
+            };
+            origin + center + rotated
+        };
+
+        tile.tile_type
+            .shapes()
+            .iter()
+            .map(move |shape| match *shape {
+                Shape::Line(start, end) => {
+                    Border::Line(LineBorder::between(rotate(start), rotate(end)))
+                }
+                Shape::Arc(center, radius, start_angle) => Border::Arc(ArcBorder {
+                    center: rotate(center),
+                    radius,
+                    start_angle: start_angle + quarters as f32 * FRAC_PI_2,
+                }),
+            })
+    }
+}
+
+pub trait RectHitbox {
+    // (x,y) the center of the hitbox
+    fn rect(&self) -> Rect;
+    fn rotation(&self) -> f32;
+
+    fn points(&self) -> [Vec2; 4] {
+        let cos_rot = self.rotation().cos();
+        let sin_rot = self.rotation().sin();
+        [
+            // TL
+            vec2(
+                (-self.rect().w * cos_rot + self.rect().h * sin_rot) * 0.5 + self.rect().x,
+                (-self.rect().w * sin_rot - self.rect().h * cos_rot) * 0.5 + self.rect().y,
+            ),
+            // TR
+            vec2(
+                (self.rect().w * cos_rot + self.rect().h * sin_rot) * 0.5 + self.rect().x,
+                (self.rect().w * sin_rot - self.rect().h * cos_rot) * 0.5 + self.rect().y,
+            ),
+            // BR
+            vec2(
+                (self.rect().w * cos_rot - self.rect().h * sin_rot) * 0.5 + self.rect().x,
+                (self.rect().w * sin_rot + self.rect().h * cos_rot) * 0.5 + self.rect().y,
+            ),
+            // BL
+            vec2(
+                (-self.rect().w * cos_rot - self.rect().h * sin_rot) * 0.5 + self.rect().x,
+                (-self.rect().w * sin_rot + self.rect().h * cos_rot) * 0.5 + self.rect().y,
+            ),
+        ]
+    }
+}
+
+impl Collider<LineBorder> for Vec2 {
+    fn collides(&self, other: LineBorder) -> bool {
+        let point = vec2(
+            self.x * other.rotation.cos() + self.y * other.rotation.sin(),
+            -self.x * other.rotation.sin() + self.y * other.rotation.cos(),
+        );
+        let start = other.rotated_start();
+        point.x > start.x && point.y < start.y && point.y > (start.y - other.lengh)
+    }
+}
+
+impl Collider<ArcBorder> for Vec2 {
+    fn collides(&self, other: ArcBorder) -> bool {
+        let offset = *self - other.center;
+        let angle = (offset.y.atan2(offset.x) - other.start_angle).rem_euclid(TAU);
+        angle <= FRAC_PI_2 && offset.length() > other.radius
+    }
+}
+
+impl Collider<Border> for Vec2 {
+    fn collides(&self, other: Border) -> bool {
+        match other {
+            Border::Line(border) => self.collides(border),
+            Border::Arc(border) => self.collides(border),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Controls of a car for one simulation step, once the deadzones are applied
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    /// in [0, 1]
+    pub accelerate: f32,
+    /// in [0, 1]
+    pub brake: f32,
+    /// in [-1, 1], positive to the right
+    pub turn: f32,
+    pub boost: bool,
+    /// asks to respawn at the last checkpoint
+    pub reset: bool,
+}
@@ -0,0 +1,270 @@
+use ahash::AHashMap;
+use macroquad::math::{vec2, Vec2};
+
+use super::{CheckpointTracker, RespawnPoint, Tile, TileType, Track, TILE_SIZE};
+
+/// Segment drawn through the middle of the `StartingLine` tiles.
+/// `forward` is the unit normal pointing in the racing direction.
+#[derive(Debug, Clone, Copy)]
+pub struct StartLine {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub forward: Vec2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crossing {
+    Forward,
+    Backward,
+}
+
+impl StartLine {
+    pub fn from_tiles(
+        tiles: &AHashMap<usize, Tile>,
+        starting_position: [usize; 2],
+    ) -> Option<Self> {
+        let line_tiles = tiles
+            .values()
+            .filter(|tile| tile.tile_type == TileType::StartingLine);
+
+        let (mut min, mut max) = (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN));
+        let mut found = false;
+        for tile in line_tiles {
+            let pos = vec2(tile.position[0] as f32, tile.position[1] as f32);
+            min = min.min(pos);
+            max = max.max(pos + 1.0);
+            found = true;
+        }
+        if !found {
+            return None;
+        }
+        let (min, max) = (min * TILE_SIZE, max * TILE_SIZE);
+        let center = (min + max) * 0.5;
+
+        let (start, end) = if max.x - min.x >= max.y - min.y {
+            (vec2(min.x, center.y), vec2(max.x, center.y))
+        } else {
+            (vec2(center.x, min.y), vec2(center.x, max.y))
+        };
+
+        // the car is placed behind the line, so the race goes from the
+        // starting position towards the line
+        let normal = (end - start).perp().normalize();
+        let spawn = vec2(
+            (starting_position[0] as f32 + 0.5) * TILE_SIZE,
+            (starting_position[1] as f32 + 0.5) * TILE_SIZE,
+        );
+        let forward = match (center - spawn).dot(normal) {
+            d if d > 0.0 => normal,
+            d if d < 0.0 => -normal,
+            // spawned on the line: use the initial heading of the car (up)
+            _ => vec2(0.0, -1.0),
+        };
+
+        Some(Self {
+            start,
+            end,
+            forward,
+        })
+    }
+
+    /// Returns the direction in which the segment `from -> to` crosses the line, if it does.
+    pub fn crossing(&self, from: Vec2, to: Vec2) -> Option<Crossing> {
+        let side_from = (from - self.start).dot(self.forward);
+        let side_to = (to - self.start).dot(self.forward);
+
+        let crossing = if side_from < 0.0 && side_to >= 0.0 {
+            Crossing::Forward
+        } else if side_from >= 0.0 && side_to < 0.0 {
+            Crossing::Backward
+        } else {
+            return None;
+        };
+
+        // check that the crossing point lies on the segment
+        let t = side_from / (side_from - side_to);
+        let point = from + (to - from) * t;
+        let dir = self.end - self.start;
+        let along = (point - self.start).dot(dir) / dir.length_squared();
+
+        (0.0..=1.0).contains(&along).then_some(crossing)
+    }
+
+    /// Middle of the line, facing the racing direction
+    pub fn respawn_point(&self) -> RespawnPoint {
+        RespawnPoint::facing((self.start + self.end) * 0.5, self.forward)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceState {
+    /// the car has not crossed the starting line yet
+    Start,
+    Racing,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaceEvent {
+    LapStarted(usize),
+    Checkpoint {
+        index: usize,
+        split: f64,
+    },
+    /// the line was crossed without passing every checkpoint
+    MissedCheckpoint,
+    LapCompleted {
+        lap: usize,
+        time: f64,
+    },
+    Finished {
+        total: f64,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct RaceProgress {
+    pub state: RaceState,
+    pub laps: usize,
+    /// current lap, starting at 1 once the line has been crossed
+    pub lap: usize,
+    pub lap_times: Vec<f64>,
+    /// checkpoint splits of every completed lap
+    pub lap_splits: Vec<Vec<f64>>,
+    /// number of times the car was put back on the track
+    pub respawns: usize,
+    lap_start: f64,
+    // number of times the line was crossed backward and not yet made up for
+    backward_crossings: usize,
+    start_line: Option<StartLine>,
+    checkpoints: CheckpointTracker,
+    // last checkpoint or line passed, the starting position before that
+    respawn_point: RespawnPoint,
+    starting_point: RespawnPoint,
+}
+
+impl RaceProgress {
+    pub fn new(track: &Track) -> Self {
+        Self {
+            state: RaceState::Start,
+            laps: track.laps.max(1),
+            lap: 0,
+            lap_times: Vec::with_capacity(track.laps),
+            lap_splits: Vec::with_capacity(track.laps),
+            respawns: 0,
+            lap_start: 0.0,
+            backward_crossings: 0,
+            start_line: track.start_line(),
+            checkpoints: CheckpointTracker::new(&track.checkpoints),
+            respawn_point: RespawnPoint::tile(track.starting_position),
+            starting_point: RespawnPoint::tile(track.starting_position),
+        }
+    }
+
+    /// Counts a respawn of the car, put back at `respawn_point`
+    pub fn respawned(&mut self) {
+        self.respawns += 1;
+        // the car is placed ahead of the line, whatever happened before
+        self.backward_crossings = 0;
+    }
+
+    /// Where to put the car back after a crash.
+    /// Falls back to the starting position if the last point passed is not on a tile.
+    pub fn respawn_point(&self, track: &Track) -> RespawnPoint {
+        if track.tile_at(self.respawn_point.position).is_some() {
+            self.respawn_point
+        } else {
+            self.starting_point
+        }
+    }
+
+    /// Splits of the checkpoints passed during the current lap
+    pub fn splits(&self) -> &[f64] {
+        &self.checkpoints.splits
+    }
+
+    pub fn checkpoint_count(&self) -> usize {
+        self.checkpoints.count()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.state == RaceState::Finished
+    }
+
+    /// Time since the beginning of the current lap
+    pub fn lap_time(&self, race_time: f64) -> f64 {
+        race_time - self.lap_start
+    }
+
+    /// Feeds the movement of the car during the last update.
+    /// `race_time` is the time elapsed since the start of the race.
+    pub fn update(&mut self, from: Vec2, to: Vec2, race_time: f64) -> Option<RaceEvent> {
+        if self.finished() {
+            return None;
+        }
+
+        let checkpoint = if self.state == RaceState::Racing {
+            let lap_time = race_time - self.lap_start;
+            self.checkpoints.update(from, to, lap_time).map(|index| {
+                if let Some(checkpoint) = self.checkpoints.get(index) {
+                    self.respawn_point = checkpoint.gate.respawn_point(from, to);
+                }
+                RaceEvent::Checkpoint {
+                    index,
+                    split: lap_time,
+                }
+            })
+        } else {
+            None
+        };
+
+        let Some(crossing) = self.start_line.and_then(|line| line.crossing(from, to)) else {
+            return checkpoint;
+        };
+
+        match crossing {
+            Crossing::Backward => {
+                self.backward_crossings += 1;
+                None
+            }
+            Crossing::Forward if self.backward_crossings > 0 => {
+                self.backward_crossings -= 1;
+                None
+            }
+            Crossing::Forward => self.cross_line(race_time),
+        }
+    }
+
+    fn cross_line(&mut self, race_time: f64) -> Option<RaceEvent> {
+        if let Some(line) = self.start_line {
+            self.respawn_point = line.respawn_point();
+        }
+
+        if self.state == RaceState::Start {
+            self.state = RaceState::Racing;
+            self.lap = 1;
+            self.lap_start = race_time;
+            return Some(RaceEvent::LapStarted(self.lap));
+        }
+
+        if !self.checkpoints.completed() {
+            return Some(RaceEvent::MissedCheckpoint);
+        }
+
+        let time = race_time - self.lap_start;
+        self.lap_times.push(time);
+        self.lap_splits.push(self.checkpoints.new_lap());
+        self.lap_start = race_time;
+
+        if self.lap == self.laps {
+            self.state = RaceState::Finished;
+            Some(RaceEvent::Finished { total: race_time })
+        } else {
+            self.lap += 1;
+            Some(RaceEvent::LapCompleted {
+                lap: self.lap - 1,
+                time,
+            })
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use macroquad::math::vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Car, CarStat, PlayerInput, RaceEvent, RaceProgress, RaceState, RacingLine, RespawnPoint, Track,
+    TILE_SIZE,
+};
+
+/// Seconds before a crashed car is moved back on the track, then before it can drive again
+pub const RESPAWN_DELAY: f32 = 0.5;
+
+/// A car taking part in a race, advanced one simulation step at a time
+#[derive(Clone, Debug)]
+pub struct Racer {
+    pub car: Car,
+    pub race: RaceProgress,
+    /// time since the end of the countdown, stopped at the finish
+    pub time: f64,
+    /// time since the car started to respawn
+    pub respawn: Option<f32>,
+    /// number of times the car left the track
+    pub crashes: usize,
+}
+
+impl Racer {
+    pub fn new(stat: CarStat, track: &Track) -> Self {
+        let mut car = Car::new(stat);
+        car.init(track.starting_position);
+        Self {
+            car,
+            race: RaceProgress::new(track),
+            time: 0.0,
+            respawn: None,
+            crashes: 0,
+        }
+    }
+
+    /// Racer placed on the starting grid: the slot 0 is the starting position of the track,
+    /// the next ones are two by two behind it
+    pub fn on_grid(stat: CarStat, track: &Track, slot: usize) -> Self {
+        let mut racer = Self::new(stat, track);
+        if slot > 0 {
+            racer.car.start_at(grid_slot(track, slot));
+        }
+        racer
+    }
+
+    pub fn step(
+        &mut self,
+        input: &PlayerInput,
+        track: &Track,
+        delta_time: f64,
+    ) -> Option<RaceEvent> {
+        if self.race.finished() {
+            return None;
+        }
+
+        if self.respawn.is_none() && (self.car.crashed || input.reset) {
+            self.respawn = Some(0.0);
+            self.crashes += self.car.crashed as usize;
+        }
+        // the car does not answer while it respawns
+        let input = if self.respawn.is_some() {
+            PlayerInput::default()
+        } else {
+            *input
+        };
+        self.car.step(&input, track, delta_time as f32);
+
+        if let Some(elapsed) = &mut self.respawn {
+            let before = *elapsed;
+            *elapsed += delta_time as f32;
+            if before < RESPAWN_DELAY && *elapsed >= RESPAWN_DELAY {
+                self.car.respawn(self.race.respawn_point(track));
+                self.race.respawned();
+            }
+            if *elapsed >= 2.0 * RESPAWN_DELAY {
+                self.respawn = None;
+            }
+        }
+
+        self.time += delta_time;
+        self.race
+            .update(self.car.previous_position, self.car.position, self.time)
+    }
+
+    /// Bumps into another car, unless one of them is out of the race for now
+    pub fn collide(&mut self, other: &mut Racer) -> bool {
+        let ghost = |racer: &Racer| {
+            racer.race.finished() || racer.respawn.is_some() || racer.car.invulnerable()
+        };
+        !ghost(self) && !ghost(other) && self.car.collide(&mut other.car)
+    }
+
+    /// The racer is ahead of `other`: it finished first, or it is further on the racing line
+    pub fn ahead_of(&self, other: &Racer, line: &RacingLine) -> bool {
+        match (self.race.finished(), other.race.finished()) {
+            (true, true) => self.time < other.time,
+            (true, false) => true,
+            (false, true) => false,
+            (false, false) => {
+                let progress = |racer: &Racer| (racer.race.lap, line.nearest(racer.car.position));
+                progress(self) > progress(other)
+            }
+        }
+    }
+
+    /// Hash of everything that makes the result of the race, to compare two runs
+    pub fn state_hash(&self) -> u64 {
+        // FNV-1a, stable across platforms and builds
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        };
+
+        let car = &self.car;
+        for value in [
+            car.position.x,
+            car.position.y,
+            car.rotation,
+            car.velocity,
+            car.knockback.x,
+            car.knockback.y,
+            car.boost,
+        ] {
+            write(&value.to_bits().to_le_bytes());
+        }
+        write(&self.time.to_bits().to_le_bytes());
+        write(&(self.race.lap as u64).to_le_bytes());
+        write(&(self.race.respawns as u64).to_le_bytes());
+        for time in self
+            .race
+            .lap_times
+            .iter()
+            .chain(self.race.lap_splits.iter().flatten())
+        {
+            write(&time.to_bits().to_le_bytes());
+        }
+        hash
+    }
+}
+
+/// What another computer needs to know about a racer to draw it and to correct its prediction
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RacerState {
+    pub position: (f32, f32),
+    pub rotation: f32,
+    pub velocity: f32,
+    pub knockback: (f32, f32),
+    pub crashed: bool,
+    pub invulnerable: f32,
+    pub boost: f32,
+    pub boosting: bool,
+    pub time: f64,
+    pub respawn: Option<f32>,
+    pub lap: usize,
+    pub finished: bool,
+}
+
+impl Racer {
+    pub fn state(&self) -> RacerState {
+        let car = &self.car;
+        RacerState {
+            position: car.position.into(),
+            rotation: car.rotation,
+            velocity: car.velocity,
+            knockback: car.knockback.into(),
+            crashed: car.crashed,
+            invulnerable: car.invulnerable,
+            boost: car.boost,
+            boosting: car.boosting,
+            time: self.time,
+            respawn: self.respawn,
+            lap: self.race.lap,
+            finished: self.race.finished(),
+        }
+    }
+
+    /// Moves the car to a state simulated elsewhere, the progress of the race is kept
+    pub fn set_state(&mut self, state: &RacerState) {
+        let car = &mut self.car;
+        car.previous_position = car.position;
+        car.previous_rotation = car.rotation;
+        car.position = state.position.into();
+        car.rotation = state.rotation;
+        car.velocity = state.velocity;
+        car.knockback = state.knockback.into();
+        car.crashed = state.crashed;
+        car.invulnerable = state.invulnerable;
+        car.boost = state.boost;
+        car.boosting = state.boosting;
+        self.time = state.time;
+        self.respawn = state.respawn;
+    }
+
+    /// Mirrors a racer simulated elsewhere, the progress of the race included
+    pub fn follow(&mut self, state: &RacerState) {
+        self.set_state(state);
+        self.race.lap = state.lap;
+        if state.finished {
+            self.race.state = RaceState::Finished;
+        }
+    }
+}
+
+/// Resolves the contacts between every pair of cars, in the order of the slice
+pub fn resolve_collisions(racers: &mut [&mut Racer]) {
+    for i in 1..racers.len() {
+        let (before, after) = racers.split_at_mut(i);
+        let racer = &mut after[0];
+        for other in before {
+            other.collide(racer);
+        }
+    }
+}
+
+/// Position of a car on the starting grid, on a tile of the track
+pub fn grid_slot(track: &Track, slot: usize) -> RespawnPoint {
+    let start = RespawnPoint::tile(track.starting_position);
+    let forward = track
+        .start_line()
+        .map_or(vec2(0.0, -1.0), |line| line.forward);
+    let row = slot.div_ceil(2) as f32;
+    let side = if slot % 2 == 1 { -1.0 } else { 1.0 };
+    let behind = start.position - forward * row * 1.5 * TILE_SIZE;
+    let position = behind + forward.perp() * side * TILE_SIZE;
+    // the middle of the row if the side is off the track
+    let position = if track.tile_at(position).is_some() {
+        position
+    } else {
+        behind
+    };
+    RespawnPoint::facing(position, forward)
+}
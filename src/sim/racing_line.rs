@@ -0,0 +1,113 @@
+use std::f32::consts::FRAC_PI_4;
+
+use macroquad::math::{vec2, Vec2};
+
+use super::{Track, TrackGraph, TILE_SIZE};
+
+// distance between two waypoints of the centerline, in tiles
+const WAYPOINT_SPACING: usize = 2;
+const OPTIMIZATION_PASSES: usize = 200;
+// distance kept between the optimized line and the tiles with a wall
+const CLEARANCE: f32 = 0.25 * TILE_SIZE;
+
+/// Waypoints to follow around the track, in the racing order.
+/// The line loops from the last waypoint back to the first one.
+#[derive(Clone, Debug, Default)]
+pub struct RacingLine {
+    pub points: Vec<Vec2>,
+}
+
+impl RacingLine {
+    pub fn new(points: Vec<Vec2>) -> Self {
+        Self { points }
+    }
+
+    /// Optimized line of the track, derived from its tiles
+    pub fn from_track(track: &Track) -> Option<Self> {
+        let graph = TrackGraph::new(track)?;
+        let mut racing_line = Self::centerline(&graph);
+        racing_line.optimize(track);
+        Some(racing_line)
+    }
+
+    /// Waypoints in the middle of the track
+    pub fn centerline(graph: &TrackGraph) -> Self {
+        Self::new(
+            graph
+                .centerline()
+                .into_iter()
+                .step_by(WAYPOINT_SPACING)
+                .collect(),
+        )
+    }
+
+    /// Moves the waypoints to spread the turns of the line over their neighbours,
+    /// as long as they stay on open tiles. The turns are then entered from the
+    /// outside and cut through their inside.
+    fn optimize(&mut self, track: &Track) {
+        let len = self.points.len();
+        if len < 3 {
+            return;
+        }
+        for _ in 0..OPTIMIZATION_PASSES {
+            self.points = (0..len)
+                .map(|i| {
+                    let point = self.points[i];
+                    let (previous, next) = (self.point(i + len - 1), self.point(i + 1));
+                    let (before, after) = (self.point(i + len - 2), self.point(i + 2));
+                    // position with the same curvature as its neighbours
+                    let smooth = ((previous + next) * 4.0 - before - after) / 6.0;
+                    let moved = point + (smooth - point) * 0.5;
+                    // the segments to the neighbours have to stay clear as well
+                    if [moved, (moved + previous) * 0.5, (moved + next) * 0.5]
+                        .into_iter()
+                        .all(|point| is_clear(track, point))
+                    {
+                        moved
+                    } else {
+                        point
+                    }
+                })
+                .collect();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Waypoint at `index`, going around the loop
+    pub fn point(&self, index: usize) -> Vec2 {
+        self.points[index % self.points.len()]
+    }
+
+    /// Index of the waypoint nearest to `position`
+    pub fn nearest(&self, position: Vec2) -> usize {
+        self.points
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.distance_squared(position)
+                    .total_cmp(&b.distance_squared(position))
+            })
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+// the point and a circle around it are on tiles without walls
+fn is_clear(track: &Track, point: Vec2) -> bool {
+    let is_open = |point: Vec2| {
+        track
+            .tile_at(point)
+            .is_some_and(|tile| tile.tile_type.is_open())
+    };
+    is_open(point)
+        && (0..8).all(|i| {
+            let angle = i as f32 * FRAC_PI_4;
+            is_open(point + vec2(angle.cos(), angle.sin()) * CLEARANCE)
+        })
+}
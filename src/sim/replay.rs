@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    resolve_collisions, AiDriver, CarStat, Difficulty, PlayerInput, Racer, RacingLine, Track,
+    SIMULATION_RATE,
+};
+
+/// Inputs of a whole race, enough to run it again step by step
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// version of the game which recorded the race
+    pub version: String,
+    pub level: String,
+    pub stat: CarStat,
+    /// cars driven by the computer, which the player may have bumped into
+    #[serde(default)]
+    pub opponents: usize,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    // each input with the number of steps it was held
+    inputs: Vec<(u32, PlayerInput)>,
+    /// state of the racer after the last input
+    pub hash: u64,
+}
+
+impl Replay {
+    pub fn new(
+        version: &str,
+        level: &str,
+        stat: CarStat,
+        opponents: usize,
+        difficulty: Difficulty,
+    ) -> Self {
+        Self {
+            version: version.to_string(),
+            level: level.to_string(),
+            stat,
+            opponents,
+            difficulty,
+            inputs: Vec::new(),
+            hash: 0,
+        }
+    }
+
+    /// Adds the input of the next simulation step
+    pub fn push(&mut self, input: PlayerInput) {
+        match self.inputs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.inputs.push((1, input)),
+        }
+    }
+
+    /// Input of every simulation step
+    pub fn inputs(&self) -> impl Iterator<Item = PlayerInput> + '_ {
+        self.inputs
+            .iter()
+            .flat_map(|(count, input)| std::iter::repeat_n(*input, *count as usize))
+    }
+
+    pub fn steps(&self) -> usize {
+        self.inputs.iter().map(|(count, _)| *count as usize).sum()
+    }
+
+    pub fn finish(&mut self, racer: &Racer) {
+        self.hash = racer.state_hash();
+    }
+
+    /// Runs the race again and checks that it ends in the same state.
+    /// The opponents are simulated in the same order as during the race.
+    pub fn verify(&self, track: &Track, racing_line: &RacingLine) -> bool {
+        let step = 1.0 / SIMULATION_RATE;
+        let mut racer = Racer::new(self.stat, track);
+        let mut opponents: Vec<(Racer, AiDriver)> = (1..=self.opponents)
+            .map(|slot| {
+                (
+                    Racer::on_grid(self.stat, track, slot),
+                    AiDriver::new(self.difficulty),
+                )
+            })
+            .collect();
+        for input in self.inputs() {
+            for (opponent, ai) in &mut opponents {
+                let input = ai.input(&opponent.car, racing_line, step as f32);
+                opponent.step(&input, track, step);
+            }
+            racer.step(&input, track, step);
+
+            let mut racers: Vec<&mut Racer> = std::iter::once(&mut racer)
+                .chain(opponents.iter_mut().map(|(opponent, _)| opponent))
+                .collect();
+            resolve_collisions(&mut racers);
+        }
+        racer.state_hash() == self.hash
+    }
+}
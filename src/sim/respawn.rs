@@ -0,0 +1,31 @@
+use std::f32::consts::FRAC_PI_4;
+
+use macroquad::math::{vec2, Vec2};
+
+use super::TILE_SIZE;
+
+/// Where and facing which direction a car is put back on the track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RespawnPoint {
+    pub position: Vec2,
+    pub rotation: f32,
+}
+
+impl RespawnPoint {
+    /// Center of a tile, facing up
+    pub fn tile(pos: [usize; 2]) -> Self {
+        Self {
+            position: (vec2(pos[0] as f32, pos[1] as f32) + 0.5) * TILE_SIZE,
+            rotation: 0.0,
+        }
+    }
+
+    /// Facing `direction`, rounded to an eighth of a turn like the tiles borders
+    pub fn facing(position: Vec2, direction: Vec2) -> Self {
+        let rotation = direction.x.atan2(-direction.y);
+        Self {
+            position,
+            rotation: (rotation / FRAC_PI_4).round() * FRAC_PI_4,
+        }
+    }
+}
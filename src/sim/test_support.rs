@@ -0,0 +1,39 @@
+use super::{CarStat, Checkpoint, Rotation, Tile, TileType, Track};
+
+/// Car of the tests, kept apart from the tuning of `config.ron`
+pub const STAT: CarStat = CarStat {
+    max_velocity: 10.0,
+    rotation_speed: 2.2,
+    acceleration: 11.68,
+    brake: 3.52,
+    hitbox_size: (3.0, 8.0),
+    restitution: 0.5,
+    wall_friction: 0.02,
+    wall_penalty: 0.2,
+    boost_charge: 0.1,
+    boost_drain: 0.5,
+    boost_acceleration: 10.0,
+    boost_velocity: 4.0,
+    collision_radius: 8.0,
+};
+
+/// Open field of drivable tiles with a starting line right above the starting position.
+/// The line is long enough to be crossed by a car turning right at full speed.
+pub fn open_field(laps: usize, checkpoints: Vec<Checkpoint>) -> Track {
+    let mut tiles = Vec::new();
+    for x in 0..70 {
+        for y in 0..60 {
+            let tile_type = if (20..=32).contains(&x) && y == 30 {
+                TileType::StartingLine
+            } else {
+                TileType::Base1
+            };
+            tiles.push(Tile {
+                position: [x, y],
+                tile_type,
+                rotation: Rotation::PiFois2,
+            });
+        }
+    }
+    Track::new([30, 31], laps, checkpoints, &tiles)
+}
@@ -0,0 +1,580 @@
+use macroquad::math::vec2;
+use macroquad::texture::Image;
+
+use super::test_support::{open_field, STAT};
+use super::*;
+
+const DT: f32 = 1.0 / SIMULATION_RATE as f32;
+
+/// Open field closed by a wall on the row 20
+fn walled_field() -> Track {
+    let mut track = open_field(3, Vec::new());
+    for x in 0..70 {
+        track.tiles.insert(
+            tile_position_flatten([x, 20]),
+            Tile {
+                position: [x, 20],
+                tile_type: TileType::StraightBorder,
+                rotation: Rotation::Pi,
+            },
+        );
+    }
+    track
+}
+
+/// Rectangular ring of drivable tiles, 4 tiles wide, raced counterclockwise from its left side
+fn ring() -> Track {
+    let mut tiles = Vec::new();
+    for x in 10..50 {
+        for y in 10..40 {
+            if (14..46).contains(&x) && (14..36).contains(&y) {
+                continue;
+            }
+            let tile_type = if x < 14 && y == 25 {
+                TileType::StartingLine
+            } else {
+                TileType::Base1
+            };
+            tiles.push(Tile {
+                position: [x, y],
+                tile_type,
+                rotation: Rotation::PiFois2,
+            });
+        }
+    }
+    Track::new([12, 27], 3, Vec::new(), &tiles)
+}
+
+fn full_throttle(turn: f32) -> PlayerInput {
+    PlayerInput {
+        accelerate: 1.0,
+        turn,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn car_crashes_when_leaving_the_track() {
+    let track = open_field(3, Vec::new());
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+
+    let mut steps = 0;
+    while !car.crashed && steps < 10_000 {
+        car.step(&full_throttle(0.0), &track, DT);
+        steps += 1;
+    }
+
+    assert!(car.crashed);
+    // the car went up until the top of the map
+    assert!(car.position.y < TILE_SIZE);
+    assert!((car.position.x - 30.5 * TILE_SIZE).abs() < 0.01);
+}
+
+#[test]
+fn car_bounces_on_walls() {
+    let track = walled_field();
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+    // the wall is 3 pixels below the top of the tiles
+    let wall = 20.0 * TILE_SIZE + 3.0;
+
+    let mut hit = None;
+    for _ in 0..600 {
+        car.step(&full_throttle(0.0), &track, DT);
+        assert!(!car.crashed);
+        assert!(car.points().iter().all(|point| point.y >= wall));
+        if car.wall_hit.is_some() && hit.is_none() {
+            hit = car.wall_hit;
+            // the car is sent back
+            assert!(car.knockback.y > 0.0);
+        }
+    }
+
+    assert!(hit.unwrap().distance(vec2(0.0, 1.0)) < 1e-3);
+}
+
+#[test]
+fn car_slides_along_walls() {
+    let track = walled_field();
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+    car.rotation = 0.5;
+
+    let mut steps = 0;
+    while car.wall_hit.is_none() {
+        car.step(&full_throttle(0.0), &track, DT);
+        steps += 1;
+        assert!(steps < 1_000);
+    }
+    let contact = car.position;
+    for _ in 0..120 {
+        car.step(&full_throttle(0.0), &track, DT);
+    }
+
+    assert!(!car.crashed);
+    assert!(car.position.x - contact.x > TILE_SIZE);
+    assert!((car.position.y - contact.y).abs() < TILE_SIZE);
+}
+
+#[test]
+fn car_respawns_at_the_last_checkpoint() {
+    let checkpoints = vec![Checkpoint {
+        index: 0,
+        gate: Gate::Tiles {
+            position: [30, 20],
+            size: [1, 1],
+        },
+    }];
+    let track = open_field(3, checkpoints);
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+    let mut race = RaceProgress::new(&track);
+
+    // before the start, the car goes back to the starting position
+    assert_eq!(race.respawn_point(&track), RespawnPoint::tile([30, 31]));
+
+    let mut time = 0.0;
+    while !car.crashed {
+        car.step(&full_throttle(0.0), &track, DT);
+        time += DT as f64;
+        race.update(car.previous_position, car.position, time);
+    }
+    car.respawn(race.respawn_point(&track));
+    race.respawned();
+
+    assert_eq!(race.respawns, 1);
+    assert_eq!(car.position, vec2(30.5, 20.5) * TILE_SIZE);
+    assert_eq!(car.rotation, 0.0);
+    assert_eq!(car.velocity, 0.0);
+    assert!(car.invulnerable());
+
+    // the respawn is not seen as a crossing of the gates
+    car.step(&PlayerInput::default(), &track, DT);
+    assert_eq!(race.update(car.previous_position, car.position, time), None);
+    assert_eq!(race.splits().len(), 1);
+}
+
+#[test]
+fn boost_raises_the_max_velocity_until_the_meter_is_empty() {
+    let track = open_field(3, Vec::new());
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+    let boost = PlayerInput {
+        boost: true,
+        ..full_throttle(1.0)
+    };
+
+    // the meter is empty at the start
+    car.step(&boost, &track, DT);
+    assert!(!car.boosting);
+
+    for _ in 0..(3.0 * SIMULATION_RATE) as usize {
+        car.step(&full_throttle(1.0), &track, DT);
+    }
+    assert!((car.boost - 0.3).abs() < 1e-3);
+    car.boost = 1.0;
+
+    let (mut top_speed, mut steps): (f32, usize) = (0.0, 0);
+    car.step(&boost, &track, DT);
+    while car.boosting {
+        top_speed = top_speed.max(car.velocity);
+        car.step(&boost, &track, DT);
+        steps += 1;
+    }
+    assert!(top_speed > STAT.max_velocity + 1.0);
+    // a full meter lasts 2 seconds
+    assert!(steps.abs_diff(2 * SIMULATION_RATE as usize) <= 1);
+
+    // the car slows down back to its max velocity
+    for _ in 0..(3.0 * SIMULATION_RATE) as usize {
+        car.step(&full_throttle(1.0), &track, DT);
+    }
+    assert!(!car.crashed);
+    assert!(car.velocity <= STAT.max_velocity);
+}
+
+#[test]
+fn movement_does_not_depend_on_the_step_rate() {
+    let track = open_field(3, Vec::new());
+    let mut slow = Car::new(STAT);
+    let mut fast = Car::new(STAT);
+    slow.init(track.starting_position);
+    fast.init(track.starting_position);
+
+    for _ in 0..60 {
+        slow.step(&full_throttle(0.5), &track, 1.0 / 60.0);
+    }
+    for _ in 0..240 {
+        fast.step(&full_throttle(0.5), &track, 1.0 / 240.0);
+    }
+
+    assert!(slow.position.distance(fast.position) < TILE_SIZE);
+    assert!((slow.rotation - fast.rotation).abs() < 1e-3);
+}
+
+#[test]
+fn race_is_finished_by_driving_in_circles() {
+    let track = open_field(3, Vec::new());
+    let mut car = Car::new(STAT);
+    car.init(track.starting_position);
+    let mut race = RaceProgress::new(&track);
+
+    let mut time = 0.0;
+    for _ in 0..10_000 {
+        car.step(&full_throttle(1.0), &track, DT);
+        time += DT as f64;
+        race.update(car.previous_position, car.position, time);
+        if race.finished() {
+            break;
+        }
+    }
+
+    assert!(!car.crashed);
+    assert!(race.finished());
+    assert_eq!(race.lap_times.len(), 3);
+    // the car is at full speed after the first lap, so the next ones take the same time
+    assert!((race.lap_times[1] - race.lap_times[2]).abs() < 0.05);
+}
+
+// largest change of direction between two parts of the line
+fn sharpest_turn(line: &RacingLine) -> f32 {
+    (0..line.len())
+        .map(|i| {
+            let a = line.point(i + 1) - line.point(i);
+            let b = line.point(i + 2) - line.point(i + 1);
+            a.angle_between(b).abs()
+        })
+        .fold(0.0, f32::max)
+}
+
+#[test]
+fn centerline_goes_around_the_track() {
+    let track = ring();
+    let graph = TrackGraph::new(&track).unwrap();
+    let line = RacingLine::centerline(&graph);
+
+    // every waypoint is on the track, the loop closing on the starting line
+    assert!(line
+        .points
+        .iter()
+        .all(|point| track.tile_at(*point).is_some()));
+    assert!(line.point(0).y < 25.0 * TILE_SIZE);
+    assert!(line.point(line.len() - 1).y > 25.0 * TILE_SIZE);
+    // the line goes up the left side, then along the top
+    assert!(line.points[line.len() / 4].y < 14.0 * TILE_SIZE);
+}
+
+#[test]
+fn optimized_line_has_wider_turns() {
+    let track = ring();
+    let centerline = RacingLine::centerline(&TrackGraph::new(&track).unwrap());
+    let line = RacingLine::from_track(&track).unwrap();
+
+    assert_eq!(line.len(), centerline.len());
+    assert!(line.points.iter().all(|point| track
+        .tile_at(*point)
+        .is_some_and(|tile| tile.tile_type.is_open())));
+    assert!(sharpest_turn(&line) < sharpest_turn(&centerline) * 0.6);
+}
+
+#[test]
+fn generated_checkpoints_cross_the_track() {
+    let track = ring();
+    let graph = TrackGraph::new(&track).unwrap();
+    let checkpoints = graph.checkpoints(&track, 3);
+    let line = RacingLine::from_track(&track).unwrap();
+
+    assert_eq!(checkpoints.len(), 3);
+    for (index, checkpoint) in checkpoints.iter().enumerate() {
+        assert_eq!(checkpoint.index, index);
+        let Gate::Line { start, end } = checkpoint.gate else {
+            panic!("checkpoints are generated as lines");
+        };
+        // from one wall to the other, 4 tiles apart
+        let length = vec2(start.0, start.1).distance(vec2(end.0, end.1));
+        assert!((3.5..5.0).contains(&length), "{length}");
+        // the racing line goes through each of them once
+        let crossings = (0..line.len())
+            .filter(|i| checkpoint.gate.passed(line.point(*i), line.point(i + 1)))
+            .count();
+        assert_eq!(crossings, 1);
+    }
+}
+
+#[test]
+fn ai_cars_finish_the_race() {
+    let mut track = ring();
+    track.checkpoints = TrackGraph::new(&track).unwrap().checkpoints(&track, 3);
+    let line = RacingLine::from_track(&track).unwrap();
+
+    let times = Difficulty::ALL.map(|difficulty| {
+        let mut racer = Racer::on_grid(STAT, &track, 1);
+        let mut ai = AiDriver::new(difficulty);
+        let mut steps = 0;
+        while !racer.race.finished() && steps < 100_000 {
+            let input = ai.input(&racer.car, &line, DT);
+            racer.step(&input, &track, DT as f64);
+            steps += 1;
+        }
+        assert!(racer.race.finished());
+        assert_eq!(racer.crashes, 0);
+        racer.time
+    });
+
+    assert!(times[0] > times[1] && times[1] > times[2]);
+}
+
+#[test]
+fn replay_reproduces_the_race() {
+    let track = open_field(3, Vec::new());
+    let mut racer = Racer::new(STAT, &track);
+    let mut replay = Replay::new("test", "open field", STAT, 0, Difficulty::Medium);
+
+    let mut step = 0;
+    while !racer.race.finished() && step < 10_000 {
+        let input = PlayerInput {
+            boost: step % 500 < 100,
+            reset: step == 300,
+            ..full_throttle(1.0)
+        };
+        replay.push(input);
+        racer.step(&input, &track, 1.0 / SIMULATION_RATE);
+        step += 1;
+    }
+    replay.finish(&racer);
+
+    assert!(racer.race.finished());
+    assert_eq!(racer.race.respawns, 1);
+    assert_eq!(replay.steps(), step);
+
+    let replay: Replay = ron::from_str(&ron::to_string(&replay).unwrap()).unwrap();
+    assert!(replay.verify(&track, &RacingLine::default()));
+
+    // one input changed makes another race
+    let mut tampered = Replay::new("test", "open field", STAT, 0, Difficulty::Medium);
+    for (i, mut input) in replay.inputs().enumerate() {
+        if i == 10 {
+            input.turn = 0.0;
+        }
+        tampered.push(input);
+    }
+    tampered.hash = replay.hash;
+    assert!(!tampered.verify(&track, &RacingLine::default()));
+}
+
+#[test]
+fn backward_crossings_are_made_up_before_counting_a_lap() {
+    let track = open_field(2, Vec::new());
+    let mut race = RaceProgress::new(&track);
+    let (before, after) = (vec2(30.5, 31.0) * TILE_SIZE, vec2(30.5, 30.0) * TILE_SIZE);
+
+    assert_eq!(
+        race.update(before, after, 1.0),
+        Some(RaceEvent::LapStarted(1))
+    );
+    assert_eq!(race.update(after, before, 2.0), None);
+    assert_eq!(race.update(before, after, 3.0), None);
+    assert_eq!(
+        race.update(before, after, 4.0),
+        Some(RaceEvent::LapCompleted { lap: 1, time: 3.0 })
+    );
+    assert_eq!(
+        race.update(before, after, 6.0),
+        Some(RaceEvent::Finished { total: 6.0 })
+    );
+}
+
+#[test]
+fn checkpoints_are_passed_in_order() {
+    let checkpoints = vec![
+        Checkpoint {
+            index: 1,
+            gate: Gate::Line {
+                start: (40.0, 10.0),
+                end: (40.0, 20.0),
+            },
+        },
+        Checkpoint {
+            index: 0,
+            gate: Gate::Tiles {
+                position: [10, 10],
+                size: [2, 2],
+            },
+        },
+    ];
+    let track = open_field(1, checkpoints);
+    let mut race = RaceProgress::new(&track);
+    let (before, after) = (vec2(30.5, 31.0) * TILE_SIZE, vec2(30.5, 30.0) * TILE_SIZE);
+    let first = vec2(11.0, 11.0) * TILE_SIZE;
+    let (left, right) = (vec2(39.0, 15.0) * TILE_SIZE, vec2(41.0, 15.0) * TILE_SIZE);
+
+    race.update(before, after, 0.0);
+    // the second gate is ignored as long as the first one was not passed
+    assert_eq!(race.update(left, right, 1.0), None);
+    assert_eq!(
+        race.update(before, after, 2.0),
+        Some(RaceEvent::MissedCheckpoint)
+    );
+
+    assert_eq!(
+        race.update(first, first, 3.0),
+        Some(RaceEvent::Checkpoint {
+            index: 0,
+            split: 3.0
+        })
+    );
+    assert_eq!(
+        race.update(right, left, 4.0),
+        Some(RaceEvent::Checkpoint {
+            index: 1,
+            split: 4.0
+        })
+    );
+    assert_eq!(
+        race.update(before, after, 5.0),
+        Some(RaceEvent::Finished { total: 5.0 })
+    );
+    assert_eq!(race.lap_splits, vec![vec![3.0, 4.0]]);
+}
+
+#[test]
+fn cars_bounce_off_each_other() {
+    let track = open_field(3, Vec::new());
+    let mut behind = Racer::new(STAT, &track);
+    let mut ahead = Racer::on_grid(STAT, &track, 1);
+    // the car behind drives into the other one, both facing up
+    ahead.car.position = behind.car.position + vec2(0.0, -10.0);
+    behind.car.velocity = 6.0;
+    ahead.car.velocity = 2.0;
+
+    assert!(behind.collide(&mut ahead));
+    assert!(behind.car.position.distance(ahead.car.position) >= 2.0 * STAT.collision_radius - 1e-3);
+    // the speed is shared, none is created
+    assert!(behind.car.velocity < ahead.car.velocity);
+    assert!((behind.car.velocity + ahead.car.velocity - 8.0).abs() < 1e-3);
+
+    // a respawning car goes through the others
+    ahead.car.position = behind.car.position;
+    ahead.respawn = Some(0.0);
+    assert!(!behind.collide(&mut ahead));
+}
+
+#[test]
+fn replay_reproduces_the_race_with_opponents() {
+    let track = ring();
+    let line = RacingLine::from_track(&track).unwrap();
+    let mut replay = Replay::new("test", "ring", STAT, 3, Difficulty::Hard);
+
+    // the player is driven by the computer too, bumping into the other cars at the start
+    let mut racer = Racer::new(STAT, &track);
+    let mut driver = AiDriver::new(Difficulty::Easy);
+    let mut opponents: Vec<(Racer, AiDriver)> = (1..=3)
+        .map(|slot| {
+            (
+                Racer::on_grid(STAT, &track, slot),
+                AiDriver::new(Difficulty::Hard),
+            )
+        })
+        .collect();
+    let mut bumps = 0;
+    while !racer.race.finished() && replay.steps() < 100_000 {
+        for (opponent, ai) in &mut opponents {
+            let input = ai.input(&opponent.car, &line, DT);
+            opponent.step(&input, &track, 1.0 / SIMULATION_RATE);
+        }
+        let input = driver.input(&racer.car, &line, DT);
+        replay.push(input);
+        racer.step(&input, &track, 1.0 / SIMULATION_RATE);
+
+        let velocity = racer.car.velocity;
+        let mut racers: Vec<&mut Racer> = std::iter::once(&mut racer)
+            .chain(opponents.iter_mut().map(|(opponent, _)| opponent))
+            .collect();
+        resolve_collisions(&mut racers);
+        bumps += (racer.car.velocity != velocity) as usize;
+    }
+    replay.finish(&racer);
+
+    assert!(racer.race.finished());
+    assert!(bumps > 0);
+    assert!(replay.verify(&track, &line));
+    // the same inputs without the opponents make another race
+    let mut alone = replay.clone();
+    alone.opponents = 0;
+    assert!(!alone.verify(&track, &line));
+}
+
+const TILE_TYPES: [TileType; 15] = [
+    TileType::StartingLine,
+    TileType::Base1,
+    TileType::Base2,
+    TileType::Base3,
+    TileType::Base4,
+    TileType::Base5,
+    TileType::Base6,
+    TileType::HardTurnInterior,
+    TileType::HardTurnExterior,
+    TileType::SoftTurnInterior,
+    TileType::SoftTurnInterior2,
+    TileType::SoftTurnExterior,
+    TileType::SoftTurnExterior2,
+    TileType::StraightBorder,
+    TileType::DiagBorder,
+];
+const ROTATIONS: [Rotation; 4] = [
+    Rotation::PiFois2,
+    Rotation::PiSur2,
+    Rotation::Pi,
+    Rotation::PiFois3Sur2,
+];
+
+/// Counts the pixels of the rotated tile where the borders disagree with the
+/// transparency of the atlas.
+fn mismatches(atlas: &Image, tile: Tile) -> usize {
+    let (source_x, source_y) = tile.tile_type.mapatlas_source();
+    let origin = vec2(tile.position[0] as f32, tile.position[1] as f32) * TILE_SIZE;
+    let size = TILE_SIZE as u32;
+
+    let mut count = 0;
+    for x in 0..size {
+        for y in 0..size {
+            // pixel of the texture displayed at (x, y) once rotated clockwise
+            let (sx, sy) = match tile.rotation {
+                Rotation::PiFois2 => (x, y),
+                Rotation::PiSur2 => (y, size - 1 - x),
+                Rotation::Pi => (size - 1 - x, size - 1 - y),
+                Rotation::PiFois3Sur2 => (size - 1 - y, x),
+            };
+            let transparent = atlas
+                .get_pixel(source_x as u32 + sx, source_y as u32 + sy)
+                .a
+                < 0.5;
+
+            let point = origin + vec2(x as f32 + 0.5, y as f32 + 0.5);
+            let off_track = Border::from_tile(&tile).any(|border| point.collides(border));
+
+            count += (transparent != off_track) as usize;
+        }
+    }
+    count
+}
+
+#[test]
+fn borders_follow_the_tiles_texture() {
+    let bytes = std::fs::read("assets/levels/biomechanical/mapatlas.png").unwrap();
+    let atlas = Image::from_file_with_format(&bytes, None).unwrap();
+
+    for tile_type in TILE_TYPES {
+        for rotation in ROTATIONS {
+            let tile = Tile {
+                position: [3, 5],
+                tile_type,
+                rotation,
+            };
+            let count = mismatches(&atlas, tile);
+            // shapes are approximations of the pixel art, allow 2% of the tile
+            assert!(count <= 12, "{tile_type:?} {rotation:?}: {count} pixels");
+        }
+    }
+}
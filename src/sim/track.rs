@@ -0,0 +1,93 @@
+use ahash::AHashMap;
+use macroquad::math::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{Checkpoint, StartLine};
+
+pub const TILE_SIZE: f32 = 24.0;
+pub const MAP_SIZE: (f32, f32) = (500.0, 250.0);
+pub const DEFAULT_LAPS: usize = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct Tile {
+    pub position: [usize; 2],
+    pub tile_type: TileType,
+    pub rotation: Rotation,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TileType {
+    StartingLine,
+    Base1,
+    Base2,
+    Base3,
+    Base4,
+    Base5,
+    Base6,
+    HardTurnInterior,
+    HardTurnExterior,
+    SoftTurnInterior,
+    SoftTurnInterior2,
+    SoftTurnExterior,
+    SoftTurnExterior2,
+    StraightBorder,
+    DiagBorder,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Rotation {
+    PiSur2 = 1,
+    Pi = 2,
+    PiFois3Sur2 = 3,
+    PiFois2 = 0,
+}
+
+/// Everything the simulation needs to know about a level
+#[derive(Clone, Debug)]
+pub struct Track {
+    pub starting_position: [usize; 2],
+    pub laps: usize,
+    pub checkpoints: Vec<Checkpoint>,
+    pub tiles: AHashMap<usize, Tile>,
+}
+
+impl Track {
+    pub fn new(
+        starting_position: [usize; 2],
+        laps: usize,
+        checkpoints: Vec<Checkpoint>,
+        tiles: &[Tile],
+    ) -> Self {
+        let mut map = AHashMap::<usize, Tile>::new();
+        tiles.iter().for_each(|tile| {
+            map.insert(tile_position_flatten(tile.position), *tile);
+        });
+
+        Self {
+            starting_position,
+            laps,
+            checkpoints,
+            tiles: map,
+        }
+    }
+
+    /// Tile under a point of the map, if there is one
+    pub fn tile_at(&self, point: Vec2) -> Option<&Tile> {
+        if point.x < 0.0 || point.y < 0.0 || point.y >= MAP_SIZE.1 * TILE_SIZE {
+            return None;
+        }
+        self.tiles.get(&tile_position_flatten([
+            (point.x / TILE_SIZE) as usize,
+            (point.y / TILE_SIZE) as usize,
+        ]))
+    }
+
+    pub fn start_line(&self) -> Option<StartLine> {
+        StartLine::from_tiles(&self.tiles, self.starting_position)
+    }
+}
+
+#[inline]
+pub fn tile_position_flatten(pos: [usize; 2]) -> usize {
+    pos[0] * MAP_SIZE.0 as usize + pos[1] % MAP_SIZE.0 as usize
+}
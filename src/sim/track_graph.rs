@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+
+use ahash::{AHashMap, AHashSet};
+use macroquad::math::{vec2, Vec2};
+
+use super::{Checkpoint, Gate, TileType, Track, TILE_SIZE};
+
+// step used to look for the ends of a generated checkpoint, in tiles
+const GATE_STEP: f32 = 0.25;
+// a generated checkpoint is never longer than this on each side of the centerline, in tiles
+const MAX_GATE_HALF_LENGTH: f32 = 8.0;
+
+/// Drivable tiles of a track, with their distance from the starting line along the lap
+#[derive(Clone, Debug)]
+pub struct TrackGraph {
+    open: AHashSet<[usize; 2]>,
+    distances: AHashMap<[usize; 2], usize>,
+    lap_length: usize,
+}
+
+impl TrackGraph {
+    /// Walks the open tiles from the starting line to the tiles right before it.
+    /// Returns `None` if the track has no starting line, or if it is not a loop.
+    pub fn new(track: &Track) -> Option<Self> {
+        let start_line = track.start_line()?;
+        let line_center = (start_line.start + start_line.end) * 0.5;
+
+        let open: AHashSet<[usize; 2]> = track
+            .tiles
+            .values()
+            .filter(|tile| tile.tile_type.is_open())
+            .map(|tile| tile.position)
+            .collect();
+        let line: AHashSet<[usize; 2]> = track
+            .tiles
+            .values()
+            .filter(|tile| tile.tile_type == TileType::StartingLine)
+            .map(|tile| tile.position)
+            .collect();
+
+        // the line is a wall: the tiles after it are only reached again by going around
+        let neighbours = |[x, y]: [usize; 2]| {
+            [
+                [x.wrapping_sub(1), y],
+                [x + 1, y],
+                [x, y.wrapping_sub(1)],
+                [x, y + 1],
+            ]
+            .into_iter()
+            .filter(|position| open.contains(position) && !line.contains(position))
+        };
+        let ahead = |position: [usize; 2]| {
+            (tile_center(position) - line_center).dot(start_line.forward) > 0.0
+        };
+
+        let mut distances: AHashMap<[usize; 2], usize> = AHashMap::new();
+        let mut queue = VecDeque::new();
+        for position in line.iter().flat_map(|tile| neighbours(*tile)) {
+            if ahead(position) && distances.insert(position, 0).is_none() {
+                queue.push_back(position);
+            }
+        }
+        while let Some(position) = queue.pop_front() {
+            let distance = distances[&position];
+            for next in neighbours(position) {
+                if !distances.contains_key(&next) {
+                    distances.insert(next, distance + 1);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        // the loop ends on the tiles right before the line
+        let lap_length = line
+            .iter()
+            .flat_map(|tile| neighbours(*tile))
+            .filter(|position| !ahead(*position))
+            .filter_map(|position| distances.get(&position).copied())
+            .min()?;
+        // tiles further than the end are dead ends
+        distances.retain(|_, distance| *distance <= lap_length);
+
+        Some(Self {
+            open,
+            distances,
+            lap_length,
+        })
+    }
+
+    /// Distance along the lap of a drivable tile, `None` if it is not part of the loop
+    pub fn distance(&self, position: [usize; 2]) -> Option<usize> {
+        self.distances.get(&position).copied()
+    }
+
+    /// Tile at this position has no wall
+    pub fn is_open(&self, position: [usize; 2]) -> bool {
+        self.open.contains(&position)
+    }
+
+    /// Middle of the track for each distance along the lap, in pixels
+    pub fn centerline(&self) -> Vec<Vec2> {
+        // every distance up to the end was reached by the walk
+        let mut sums = vec![(Vec2::ZERO, 0.0); self.lap_length + 1];
+        for (position, distance) in &self.distances {
+            let (sum, count) = &mut sums[*distance];
+            *sum += tile_center(*position);
+            *count += 1.0;
+        }
+        sums.iter().map(|(sum, count)| *sum / *count).collect()
+    }
+
+    /// Checkpoints spread evenly along the lap, as lines going across the track
+    pub fn checkpoints(&self, track: &Track, count: usize) -> Vec<Checkpoint> {
+        let centerline = self.centerline();
+        if self.lap_length < count + 2 {
+            return Vec::new();
+        }
+
+        (0..count)
+            .filter_map(|index| {
+                let at = (index + 1) * self.lap_length / (count + 1);
+                let center = centerline[at];
+                let direction = centerline[at + 1] - centerline[at - 1];
+                let across = direction.perp().try_normalize()?;
+
+                // a gate stops where the track does, or on another part of the lap
+                let on_this_part = |point: Vec2| {
+                    let Some(tile) = track.tile_at(point) else {
+                        return false;
+                    };
+                    !self.is_open(tile.position)
+                        || self
+                            .distance(tile.position)
+                            .is_some_and(|distance| distance.abs_diff(at) <= self.lap_length / 8)
+                };
+                let end = |side: f32| {
+                    let mut length = 0.0;
+                    while length < MAX_GATE_HALF_LENGTH
+                        && on_this_part(center + across * side * (length + GATE_STEP) * TILE_SIZE)
+                    {
+                        length += GATE_STEP;
+                    }
+                    (center / TILE_SIZE + across * side * length).into()
+                };
+
+                Some(Gate::Line {
+                    start: end(-1.0),
+                    end: end(1.0),
+                })
+            })
+            .enumerate()
+            .map(|(index, gate)| Checkpoint { index, gate })
+            .collect()
+    }
+}
+
+fn tile_center(position: [usize; 2]) -> Vec2 {
+    (vec2(position[0] as f32, position[1] as f32) + 0.5) * TILE_SIZE
+}